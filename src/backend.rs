@@ -0,0 +1,85 @@
+use crate::dynamics::Position;
+
+/// Affine transform as in Canvas2D: `x' = a x + c y + e`, `y' = b x + d y + f`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+
+    pub fn translation(x: f64, y: f64) -> Transform {
+        Transform { e: x, f: y, ..Transform::IDENTITY }
+    }
+
+    pub fn scaling(x: f64, y: f64) -> Transform {
+        Transform { a: x, d: y, ..Transform::IDENTITY }
+    }
+
+    /// Clockwise on screen, as the y axis points down
+    pub fn rotation(angle: f64) -> Transform {
+        let (sin, cos) = angle.sin_cos();
+        Transform { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }
+    }
+
+    /// Applies `other` before this transform, like the Canvas2D methods do
+    pub fn then(&self, other: Transform) -> Transform {
+        Transform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        Some(Transform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+}
+
+/// Drawing primitives used by `system_renderer`. Coordinates go through the current transform,
+/// which `save` and `restore` push and pop like in a Canvas2D context
+pub trait Backend {
+    /// Erases the whole surface
+    fn clear(&mut self);
+    fn save(&mut self);
+    fn restore(&mut self);
+    fn translate(&mut self, x: f64, y: f64);
+    fn scale(&mut self, x: f64, y: f64);
+    /// Clockwise rotation in radians, as the y axis points down
+    fn rotate(&mut self, angle: f64);
+    fn circle(&mut self, center: &Position, radius: f64, color: &str);
+    /// Filled band between two circles
+    fn ring(&mut self, center: &Position, inner: f64, outer: f64, color: &str);
+    /// `position` is the top left corner
+    fn rect(&mut self, position: &Position, width: f64, height: f64, color: &str);
+    /// `position` is the start of the baseline
+    fn text(&mut self, text: &str, position: &Position, font: &str, color: &str);
+    /// Draws the image stretched to `width` by `height` from its top left corner
+    fn image(&mut self, image: &str, position: &Position, width: f64, height: f64);
+    /// Size in pixels of a loaded image
+    fn image_size(&self, image: &str) -> Option<(f64, f64)>;
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::backend::{Backend, Transform};
+use crate::dynamics::Position;
+use crate::raster::{MISSING_COLOR, parse_color};
+
+/// Floats per quad vertex: screen position, atlas coordinates and colour
+pub const QUAD_FLOATS: usize = 8;
+/// Floats per circle instance: screen centre, the two transformed radius axes, inner radius and colour
+pub const CIRCLE_FLOATS: usize = 11;
+/// Atlas coordinates of untextured quads
+const NO_TEXTURE: f32 = -1.0;
+/// Empty pixels kept around every image so linear filtering does not bleed into its neighbours
+const ATLAS_PADDING: u32 = 1;
+
+/// Area of an image inside a texture, in pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Region {
+    /// 0 is the atlas, the images that do not fit in it have a page of their own starting at 1
+    pub page: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Packs images into a square texture, left to right in rows, in the order they are loaded
+pub struct Atlas {
+    pub size: u32,
+    regions: HashMap<String, Region>,
+    cursor: (u32, u32),
+    row_height: u32,
+    pages: u32,
+}
+
+impl Atlas {
+    pub fn new(size: u32) -> Self {
+        Atlas {
+            size,
+            regions: HashMap::new(),
+            cursor: (0, 0),
+            row_height: 0,
+            pages: 0,
+        }
+    }
+
+    pub fn get(&self, image: &str) -> Option<Region> {
+        self.regions.get(image).copied()
+    }
+
+    /// Finds room for an image. `None` if it does not fit in what is left of the atlas, which is then left as it was
+    pub fn insert(&mut self, image: &str, width: u32, height: u32) -> Option<Region> {
+        if let Some(region) = self.get(image) {
+            return Some(region);
+        }
+        let (padded_width, padded_height) = (width + 2 * ATLAS_PADDING, height + 2 * ATLAS_PADDING);
+        if padded_width > self.size {
+            return None;
+        }
+        let (cursor, row_height) = if self.cursor.0 + padded_width > self.size {
+            ((0, self.cursor.1 + self.row_height), 0)
+        } else {
+            (self.cursor, self.row_height)
+        };
+        if cursor.1 + padded_height > self.size {
+            return None;
+        }
+        let region = Region {
+            page: 0,
+            x: cursor.0 + ATLAS_PADDING,
+            y: cursor.1 + ATLAS_PADDING,
+            width,
+            height,
+        };
+        self.cursor = (cursor.0 + padded_width, cursor.1);
+        self.row_height = row_height.max(padded_height);
+        self.regions.insert(image.to_string(), region);
+        Some(region)
+    }
+
+    /// Gives an image that does not fit a page of its own, covering all of it
+    pub fn insert_alone(&mut self, image: &str, width: u32, height: u32) -> Region {
+        if let Some(region) = self.get(image) {
+            return region;
+        }
+        self.pages += 1;
+        let region = Region {
+            page: self.pages,
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        self.regions.insert(image.to_string(), region);
+        region
+    }
+}
+
+/// Consecutive primitives of the same kind, drawn with a single call
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Run {
+    /// `count` vertices starting at vertex `start`, textured from atlas page `page`
+    Quads { start: usize, count: usize, page: u32 },
+    /// `count` instances starting at instance `start`
+    Circles { start: usize, count: usize },
+    /// `count` labels starting at label `start`
+    Text { start: usize, count: usize },
+}
+
+/// Text is not turned into vertices, a 2D context draws each run of labels in its place
+#[derive(Clone, PartialEq, Debug)]
+pub struct Label {
+    pub text: String,
+    pub font: String,
+    pub color: String,
+    /// Puts the start of the baseline at the origin
+    pub transform: Transform,
+}
+
+/// Backend that turns a frame into vertex data for the GPU. Quads cover rects and atlas images,
+/// circles and rings are instances of one quad cut out in the shader. Runs keep the drawing order
+pub struct Batch {
+    pub quads: Vec<f32>,
+    pub circles: Vec<f32>,
+    pub runs: Vec<Run>,
+    pub labels: Vec<Label>,
+    pub atlas: Atlas,
+    transform: Transform,
+    stack: Vec<Transform>,
+}
+
+fn rgba(color: &str) -> [f32; 4] {
+    let [r, g, b, a] = parse_color(color).unwrap_or(MISSING_COLOR);
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0]
+}
+
+impl Batch {
+    pub fn new(atlas: Atlas) -> Self {
+        Batch {
+            quads: Vec::new(),
+            circles: Vec::new(),
+            runs: Vec::new(),
+            labels: Vec::new(),
+            atlas,
+            transform: Transform::IDENTITY,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Extends the last run if it is of the same kind and texture, otherwise starts `run`
+    fn push_run(&mut self, run: Run) {
+        match (self.runs.last_mut(), run) {
+            (Some(Run::Quads { count, page, .. }), Run::Quads { count: added, page: new, .. }) if *page == new => *count += added,
+            (Some(Run::Circles { count, .. }), Run::Circles { count: added, .. }) => *count += added,
+            (Some(Run::Text { count, .. }), Run::Text { count: added, .. }) => *count += added,
+            _ => self.runs.push(run),
+        }
+    }
+
+    /// Two triangles covering the local rectangle, `uv` being the atlas coordinates of its corners
+    fn quad(&mut self, position: &Position, width: f64, height: f64, page: u32, uv: [f32; 4], color: [f32; 4]) {
+        let corners = [
+            (0.0, 0.0, uv[0], uv[1]),
+            (width, 0.0, uv[2], uv[1]),
+            (0.0, height, uv[0], uv[3]),
+            (width, 0.0, uv[2], uv[1]),
+            (width, height, uv[2], uv[3]),
+            (0.0, height, uv[0], uv[3]),
+        ];
+        for (x, y, u, v) in corners.iter() {
+            let (x, y) = self.transform.apply(position.x + x, position.y + y);
+            self.quads.extend_from_slice(&[x as f32, y as f32, *u, *v]);
+            self.quads.extend_from_slice(&color);
+        }
+        self.push_run(Run::Quads { start: self.quads.len() / QUAD_FLOATS - 6, count: 6, page });
+    }
+}
+
+impl Backend for Batch {
+    /// Starts a new frame. The atlas is kept
+    fn clear(&mut self) {
+        self.quads.clear();
+        self.circles.clear();
+        self.runs.clear();
+        self.labels.clear();
+        self.transform = Transform::IDENTITY;
+        self.stack.clear();
+    }
+
+    fn save(&mut self) {
+        self.stack.push(self.transform);
+    }
+
+    fn restore(&mut self) {
+        if let Some(transform) = self.stack.pop() {
+            self.transform = transform;
+        }
+    }
+
+    fn translate(&mut self, x: f64, y: f64) {
+        self.transform = self.transform.then(Transform::translation(x, y));
+    }
+
+    fn scale(&mut self, x: f64, y: f64) {
+        self.transform = self.transform.then(Transform::scaling(x, y));
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.transform = self.transform.then(Transform::rotation(angle));
+    }
+
+    fn circle(&mut self, center: &Position, radius: f64, color: &str) {
+        self.ring(center, 0.0, radius, color);
+    }
+
+    fn ring(&mut self, center: &Position, inner: f64, outer: f64, color: &str) {
+        if outer <= 0.0 {
+            return;
+        }
+        let t = self.transform;
+        let (x, y) = t.apply(center.x, center.y);
+        self.circles.extend_from_slice(&[
+            x as f32, y as f32,
+            (t.a * outer) as f32, (t.b * outer) as f32,
+            (t.c * outer) as f32, (t.d * outer) as f32,
+            (inner / outer) as f32,
+        ]);
+        self.circles.extend_from_slice(&rgba(color));
+        self.push_run(Run::Circles { start: self.circles.len() / CIRCLE_FLOATS - 1, count: 1 });
+    }
+
+    fn rect(&mut self, position: &Position, width: f64, height: f64, color: &str) {
+        // Untextured quads can join a run of any page
+        let page = match self.runs.last() {
+            Some(Run::Quads { page, .. }) => *page,
+            _ => 0,
+        };
+        self.quad(position, width, height, page, [NO_TEXTURE; 4], rgba(color));
+    }
+
+    fn text(&mut self, text: &str, position: &Position, font: &str, color: &str) {
+        self.labels.push(Label {
+            text: text.to_string(),
+            font: font.to_string(),
+            color: color.to_string(),
+            transform: self.transform.then(Transform::translation(position.x, position.y)),
+        });
+        self.push_run(Run::Text { start: self.labels.len() - 1, count: 1 });
+    }
+
+    fn image(&mut self, image: &str, position: &Position, width: f64, height: f64) {
+        if let Some(region) = self.atlas.get(image) {
+            let uv = if region.page == 0 {
+                let size = self.atlas.size as f32;
+                [
+                    region.x as f32 / size,
+                    region.y as f32 / size,
+                    (region.x + region.width) as f32 / size,
+                    (region.y + region.height) as f32 / size,
+                ]
+            } else {
+                [0.0, 0.0, 1.0, 1.0]
+            };
+            self.quad(position, width, height, region.page, uv, [1.0; 4]);
+        }
+    }
+
+    fn image_size(&self, image: &str) -> Option<(f64, f64)> {
+        self.atlas.get(image).map(|region| (region.width as f64, region.height as f64))
+    }
+}
@@ -0,0 +1,40 @@
+//! Validates a level file: `cargo run --bin lint -- [src/levels/levels.ron] [--public public]`
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use orbital_bus::levels::lint;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut path = PathBuf::from("src/levels/levels.ron");
+    let mut public = PathBuf::from("public");
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--public" => public = args.next().map(PathBuf::from).expect("--public needs a directory"),
+            "--help" => {
+                println!("usage: lint [FILE] [--public DIR]\nChecks a level pack, src/levels/levels.ron by default. Its format is described in docs/levels.md");
+                return;
+            },
+            _ => path = PathBuf::from(arg),
+        }
+    }
+
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            process::exit(2);
+        }
+    };
+    let issues = lint(&source, |sprite| public.join(sprite).is_file());
+    for issue in &issues {
+        println!("{}:{}", path.display(), issue);
+    }
+    if !issues.is_empty() {
+        process::exit(1);
+    }
+    println!("{}: ok", path.display());
+}
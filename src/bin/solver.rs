@@ -0,0 +1,62 @@
+//! Checks that a level can be won: `cargo run --release --bin solver -- <level> [--pack FILE] [--beam N] [--save FILE]`
+
+use std::env;
+use std::fs;
+use std::process;
+
+use orbital_bus::levels::Levels;
+use orbital_bus::solver::{SolverOptions, solve};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut level = None;
+    let mut save = None;
+    let mut pack = None;
+    let mut options = SolverOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--beam" => options.beam = args.next().and_then(|n| n.parse().ok()).expect("--beam needs a number"),
+            "--seconds" => options.max_seconds = args.next().and_then(|n| n.parse().ok()).expect("--seconds needs a number"),
+            "--save" => save = args.next(),
+            "--pack" => pack = args.next(),
+            _ => level = arg.parse().ok(),
+        }
+    }
+    let level: i32 = match level {
+        Some(level) => level,
+        None => {
+            eprintln!("usage: solver <level> [--pack FILE] [--beam N] [--seconds S] [--save FILE]");
+            process::exit(2);
+        }
+    };
+
+    let mut levels = Levels::builtin();
+    if let Some(path) = &pack {
+        let added = fs::read_to_string(path).map_err(|error| error.to_string())
+            .and_then(|source| levels.add_pack(&source).map_err(|error| error.to_string()));
+        if let Err(error) = added {
+            eprintln!("{}: {}", path, error);
+            process::exit(2);
+        }
+    }
+
+    match solve(&levels, level, &options) {
+        Some(solution) => {
+            println!("level {}: reachable", level);
+            if pack.is_some() {
+                // Replays only reproduce the run against the same levels, so name the pack they need
+                println!("pack: {}", levels.packs()[0]);
+            }
+            println!("fuel used: {:.2}", solution.fuel);
+            println!("inputs: {}", solution.recording.encode());
+            if let Some(path) = save {
+                fs::write(&path, solution.recording.encode()).expect("unable to write the replay");
+                println!("saved to {}", path);
+            }
+        },
+        None => {
+            println!("level {}: no winning run found", level);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::f64;
+
+use wasm_bindgen::prelude::*;
+
+use crate::ImageStore;
+use crate::backend::Backend;
+use crate::dynamics::*;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+/// Draws on the browser canvas through its 2D context
+pub struct Canvas<'a> {
+    context: &'a web_sys::CanvasRenderingContext2d,
+    store: &'a ImageStore,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(context: &'a web_sys::CanvasRenderingContext2d, store: &'a ImageStore) -> Self {
+        Canvas {
+            context,
+            store,
+        }
+    }
+}
+
+impl Backend for Canvas<'_> {
+    fn clear(&mut self) {
+        self.context.clear_rect(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT);
+    }
+
+    fn save(&mut self) {
+        self.context.save();
+    }
+
+    fn restore(&mut self) {
+        self.context.restore();
+    }
+
+    fn translate(&mut self, x: f64, y: f64) {
+        self.context.translate(x, y).unwrap();
+    }
+
+    fn scale(&mut self, x: f64, y: f64) {
+        self.context.scale(x, y).unwrap();
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.context.rotate(angle).unwrap();
+    }
+
+    fn circle(&mut self, center: &Position, radius: f64, color: &str) {
+        let context = self.context;
+        context.begin_path();
+        context.set_fill_style(&JsValue::from_str(color));
+        context
+            .arc(center.x, center.y, radius, 0.0, f64::consts::PI * 2.0)
+            .unwrap();
+        context.fill();
+    }
+
+    fn ring(&mut self, center: &Position, inner: f64, outer: f64, color: &str) {
+        let context = self.context;
+        context.begin_path();
+        context.set_fill_style(&JsValue::from_str(color));
+        context
+            .arc(center.x, center.y, outer, 0.0, f64::consts::PI * 2.0)
+            .unwrap();
+        // Drawing the inner circle the other way around leaves it as a hole
+        context.move_to(center.x + inner, center.y);
+        context
+            .arc_with_anticlockwise(center.x, center.y, inner, 0.0, -f64::consts::PI * 2.0, true)
+            .unwrap();
+        context.fill();
+    }
+
+    fn rect(&mut self, position: &Position, width: f64, height: f64, color: &str) {
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.fill_rect(position.x, position.y, width, height);
+    }
+
+    fn text(&mut self, text: &str, position: &Position, font: &str, color: &str) {
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.set_font(font);
+        self.context.fill_text(text, position.x, position.y).unwrap();
+    }
+
+    fn image(&mut self, image: &str, position: &Position, width: f64, height: f64) {
+        if let Some(image) = self.store.get(image) {
+            self.context.draw_image_with_html_image_element_and_dw_and_dh(image, position.x, position.y, width, height).unwrap();
+        }
+    }
+
+    fn image_size(&self, image: &str) -> Option<(f64, f64)> {
+        self.store.get(image).map(|image| (image.width() as f64, image.height() as f64))
+    }
+}
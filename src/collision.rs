@@ -0,0 +1,82 @@
+use crate::dynamics::Position;
+
+/// Point and moment, as a fraction of the physics step, where the spaceship hit something
+#[derive(Clone)]
+pub struct Impact {
+    pub x: f64,
+    pub y: f64,
+    pub time: f64,
+}
+
+/// Earliest fraction of the movement `from -> to` that lies inside the circle, if any.
+/// Grazing the edge is a miss, as for `segment_rect`
+pub fn segment_circle(from: &Position, to: &Position, center: &Position, radius: f64) -> Option<f64> {
+    let fx = from.x - center.x;
+    let fy = from.y - center.y;
+    let c = fx * fx + fy * fy - radius * radius;
+    if c < 0.0 {
+        return Some(0.0);
+    }
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    let a = dx * dx + dy * dy;
+    if a == 0.0 {
+        return None;
+    }
+    let b = 2.0 * (fx * dx + fy * dy);
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant <= 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    if (0.0..=1.0).contains(&t) {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Earliest fraction of the movement `from -> to` that lies inside the axis aligned rectangle, if any
+pub fn segment_rect(from: &Position, to: &Position, min: &Position, max: &Position) -> Option<f64> {
+    let mut enter: f64 = 0.0;
+    let mut exit: f64 = 1.0;
+    for (start, delta, low, high) in [(from.x, to.x - from.x, min.x, max.x), (from.y, to.y - from.y, min.y, max.y)] {
+        if delta == 0.0 {
+            if start <= low || start >= high {
+                return None;
+            }
+        } else {
+            let t0 = (low - start) / delta;
+            let t1 = (high - start) / delta;
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+    }
+    if enter < exit {
+        Some(enter)
+    } else {
+        None
+    }
+}
+
+/// Earliest fraction of the movement `from -> to` that lies between the two circles, if any
+pub fn segment_annulus(from: &Position, to: &Position, center: &Position, inner: f64, outer: f64) -> Option<f64> {
+    let time = segment_circle(from, to, center, outer)?;
+    let entry = lerp(from, to, time);
+    if (entry.x - center.x).hypot(entry.y - center.y) >= inner {
+        return Some(time);
+    }
+    // Started inside the hole, the band is reached when leaving it
+    match segment_circle(to, from, center, inner) {
+        Some(back) if back > 0.0 => Some(1.0 - back),
+        _ => None,
+    }
+}
+
+/// Position reached after a fraction `time` of the movement `from -> to`
+pub fn lerp(from: &Position, to: &Position, time: f64) -> Position {
+    Position {
+        x: from.x + (to.x - from.x) * time,
+        y: from.y + (to.y - from.y) * time,
+    }
+}
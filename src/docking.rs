@@ -0,0 +1,46 @@
+use std::f64::consts::PI;
+
+/// Tolerances to dock with an end zone. Without them the zone counts at any speed
+#[derive(Clone)]
+pub struct Dock {
+    pub max_speed: f64,
+    /// Maximum deviation of the approach from `direction`, in radians. Any approach is fine if missing
+    pub max_angle: Option<f64>,
+    pub direction: f64,
+    /// Speed of the spaceship relative to the zone during the last physics step
+    pub relative_speed: f64,
+    /// Direction of the relative velocity during the last physics step
+    pub approach: f64,
+}
+
+impl Dock {
+    pub fn new(max_speed: f64, max_angle: Option<f64>, direction: f64) -> Self {
+        Dock {
+            max_speed,
+            max_angle,
+            direction,
+            relative_speed: 0.0,
+            approach: 0.0,
+        }
+    }
+
+    /// Updates the readouts from the velocities of the spaceship and the zone and tells if docking is allowed
+    pub fn check(&mut self, ship: (f64, f64), zone: (f64, f64)) -> bool {
+        let x = ship.0 - zone.0;
+        let y = ship.1 - zone.1;
+        self.relative_speed = x.hypot(y);
+        self.approach = y.atan2(x);
+        self.relative_speed <= self.max_speed && self.max_angle.is_none_or(|max_angle| self.deviation() <= max_angle)
+    }
+
+    /// Angle between the last approach and `direction`, in radians
+    pub fn deviation(&self) -> f64 {
+        let mut deviation = (self.approach - self.direction) % (2.0 * PI);
+        if deviation > PI {
+            deviation -= 2.0 * PI;
+        } else if deviation < -PI {
+            deviation += 2.0 * PI;
+        }
+        deviation.abs()
+    }
+}
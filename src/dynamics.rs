@@ -1,6 +1,14 @@
-use hecs::{With, World};
+use std::collections::HashMap;
+use std::f64::consts::PI;
 
-use crate::{SpaceShip, Finish};
+use hecs::{Entity, With, Without, World};
+use serde::Deserialize;
+
+use crate::{SpaceShip, EndZone, Finish, ZoneShape};
+use crate::collision::*;
+use crate::docking::Dock;
+use crate::landing::{Landed, touchdown};
+use crate::transform::chained_position;
 
 #[derive(Clone)]
 pub struct Position {
@@ -8,6 +16,14 @@ pub struct Position {
     pub y: f64,
 }
 
+/// Position at the previous physics step, used to interpolate rendering between steps
+#[derive(Clone)]
+pub struct PreviousPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone)]
 pub struct Velocity {
     pub x: f64,
     pub y: f64,
@@ -19,32 +35,374 @@ pub struct Celestial {
     pub radius: f64,
 }
 
+/// Numerical method used by `system_gravity` to advance bodies
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Integrator {
+    /// Position with the old velocity, then velocity. Gains energy over time
+    Euler,
+    /// Velocity first, then position with the new velocity. Original behaviour
+    #[default]
+    SemiImplicitEuler,
+    /// Velocity Verlet (leapfrog), keeps orbital energy bounded
+    Verlet,
+    /// Classic fourth order Runge-Kutta
+    Rk4,
+}
+
+/// Force law used to compute the pull of celestial bodies
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Gravity {
+    /// Linear pull `(celestial - position) * mass`, grows with distance
+    #[default]
+    Classic,
+    /// Inverse square law `g * mass / r^2`, with `softening` avoiding the singularity at r = 0
+    Newton {
+        g: f64,
+        softening: f64,
+    },
+}
+
+/// Per level physics settings, spawned once in the world
+#[derive(Clone, Default)]
+pub struct Physics {
+    pub integrator: Integrator,
+    pub gravity: Gravity,
+    /// Moving celestial bodies attract each other instead of only feeling the initial snapshot
+    pub nbody: bool,
+}
+
+/// Atmosphere band of a celestial body, from its surface up to `radius`, where drag slows bodies down
+#[derive(Clone)]
+pub struct Atmosphere {
+    pub planet: Entity,
+    pub radius: f64,
+    pub density: f64,
+}
+
+/// Keplerian "on-rails" orbit around a parent body, computed analytically instead of integrated
+#[derive(Clone)]
+pub struct Orbit {
+    pub parent: Entity,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    /// Mean anomaly at the start of the level, in radians
+    pub phase: f64,
+    /// Angle of the periapsis, in radians
+    pub periapsis: f64,
+    /// Direction of motion as seen on screen
+    pub clockwise: bool,
+    pub period: f64,
+    pub time: f64,
+}
+
+impl Orbit {
+    /// Offset from the parent body at the current time
+    pub fn offset(&self) -> Position {
+        let mean_anomaly = self.phase + 2.0 * PI * self.time / self.period;
+        orbit_offset(self.semi_major_axis, self.eccentricity, self.periapsis, self.clockwise, mean_anomaly)
+    }
+}
+
+/// Offset from the parent body of a Keplerian orbit at the given mean anomaly, angles in radians
+pub fn orbit_offset(semi_major_axis: f64, eccentricity: f64, periapsis: f64, clockwise: bool, mean_anomaly: f64) -> Position {
+    let e = eccentricity;
+    let mut eccentric_anomaly = mean_anomaly;
+    for _ in 0..10 {
+        eccentric_anomaly -= (eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly) / (1.0 - e * eccentric_anomaly.cos());
+    }
+    let semi_minor_axis = semi_major_axis * (1.0 - e * e).sqrt();
+    let x = semi_major_axis * (eccentric_anomaly.cos() - e);
+    let mut y = semi_minor_axis * eccentric_anomaly.sin();
+    if !clockwise {
+        y = -y;
+    }
+    Position {
+        x: x * periapsis.cos() - y * periapsis.sin(),
+        y: x * periapsis.sin() + y * periapsis.cos(),
+    }
+}
+
+/// Period of an orbit around a body of the given mass, matching the force law of the level
+pub fn orbital_period(gravity: Gravity, mass: f64, semi_major_axis: f64) -> f64 {
+    match gravity {
+        Gravity::Classic => 2.0 * PI / mass.sqrt(),
+        Gravity::Newton { g, .. } => 2.0 * PI * (semi_major_axis.powi(3) / (g * mass)).sqrt(),
+    }
+}
+
+pub fn system_previous_position(world: &mut World) {
+    for (_id, (position, previous)) in &mut world.query::<(&Position, &mut PreviousPosition)>() {
+        previous.x = position.x;
+        previous.y = position.y;
+    }
+}
+
+fn acceleration(gravity: Gravity, position: &Position, planets: &[(Position, Celestial)]) -> (f64, f64) {
+    planets.iter().fold((0.0, 0.0), |(ax, ay), (celestial_position, celestial)| {
+        let dx = celestial_position.x - position.x;
+        let dy = celestial_position.y - position.y;
+        match gravity {
+            Gravity::Classic => (ax + dx * celestial.mass, ay + dy * celestial.mass),
+            Gravity::Newton { g, softening } => {
+                let r2 = dx * dx + dy * dy + softening * softening;
+                if r2 == 0.0 {
+                    return (ax, ay);
+                }
+                let f = g * celestial.mass / (r2 * r2.sqrt());
+                (ax + dx * f, ay + dy * f)
+            },
+        }
+    })
+}
+
+fn advance(positions: &[Position], rates: &[(f64, f64)], delta: f64) -> Vec<Position> {
+    positions.iter().zip(rates).map(|(position, (rx, ry))| Position {
+        x: position.x + rx * delta,
+        y: position.y + ry * delta,
+    }).collect()
+}
+
+/// Advances a set of bodies one step. `accelerations` returns the acceleration of every body given all their positions,
+/// so bodies that attract each other are integrated together.
+fn integrate<F>(integrator: Integrator, positions: &mut [Position], velocities: &mut [Velocity], delta: f64, accelerations: F)
+where
+    F: Fn(&[Position]) -> Vec<(f64, f64)>
+{
+    let speeds = |velocities: &[Velocity]| -> Vec<(f64, f64)> {
+        velocities.iter().map(|velocity| (velocity.x, velocity.y)).collect()
+    };
+    match integrator {
+        Integrator::Euler => {
+            let a = accelerations(positions);
+            for ((position, velocity), (ax, ay)) in positions.iter_mut().zip(velocities.iter_mut()).zip(a) {
+                position.x += velocity.x * delta;
+                position.y += velocity.y * delta;
+                velocity.x += ax * delta;
+                velocity.y += ay * delta;
+            }
+        },
+        Integrator::SemiImplicitEuler => {
+            let a = accelerations(positions);
+            for ((position, velocity), (ax, ay)) in positions.iter_mut().zip(velocities.iter_mut()).zip(a) {
+                velocity.x += ax * delta;
+                velocity.y += ay * delta;
+                position.x += velocity.x * delta;
+                position.y += velocity.y * delta;
+            }
+        },
+        Integrator::Verlet => {
+            let a = accelerations(positions);
+            for ((position, velocity), (ax, ay)) in positions.iter_mut().zip(velocities.iter()).zip(&a) {
+                position.x += velocity.x * delta + 0.5 * ax * delta * delta;
+                position.y += velocity.y * delta + 0.5 * ay * delta * delta;
+            }
+            let na = accelerations(positions);
+            for ((velocity, (ax, ay)), (nax, nay)) in velocities.iter_mut().zip(a).zip(na) {
+                velocity.x += 0.5 * (ax + nax) * delta;
+                velocity.y += 0.5 * (ay + nay) * delta;
+            }
+        },
+        Integrator::Rk4 => {
+            let k1v = speeds(velocities);
+            let k1a = accelerations(positions);
+            let k2v: Vec<(f64, f64)> = k1v.iter().zip(&k1a).map(|((vx, vy), (ax, ay))| (vx + ax * delta / 2.0, vy + ay * delta / 2.0)).collect();
+            let k2a = accelerations(&advance(positions, &k1v, delta / 2.0));
+            let k3v: Vec<(f64, f64)> = k1v.iter().zip(&k2a).map(|((vx, vy), (ax, ay))| (vx + ax * delta / 2.0, vy + ay * delta / 2.0)).collect();
+            let k3a = accelerations(&advance(positions, &k2v, delta / 2.0));
+            let k4v: Vec<(f64, f64)> = k1v.iter().zip(&k3a).map(|((vx, vy), (ax, ay))| (vx + ax * delta, vy + ay * delta)).collect();
+            let k4a = accelerations(&advance(positions, &k3v, delta));
+            for i in 0..positions.len() {
+                positions[i].x += (k1v[i].0 + 2.0 * k2v[i].0 + 2.0 * k3v[i].0 + k4v[i].0) * delta / 6.0;
+                positions[i].y += (k1v[i].1 + 2.0 * k2v[i].1 + 2.0 * k3v[i].1 + k4v[i].1) * delta / 6.0;
+                velocities[i].x += (k1a[i].0 + 2.0 * k2a[i].0 + 2.0 * k3a[i].0 + k4a[i].0) * delta / 6.0;
+                velocities[i].y += (k1a[i].1 + 2.0 * k2a[i].1 + 2.0 * k3a[i].1 + k4a[i].1) * delta / 6.0;
+            }
+        },
+    }
+}
+
+/// Moves every body that has a velocity independently, pulled by a snapshot of the celestial bodies.
+/// In n-body mode the moving celestial bodies are left to `integrate_nbody`.
+fn integrate_free(physics: &Physics, world: &mut World, delta: f64, planets: &[(Position, Celestial)]) {
+    for (_id, (position, velocity, celestial)) in &mut world.query::<(&mut Position, &mut Velocity, Option<&Celestial>)>() {
+        if physics.nbody && celestial.is_some() {
+            continue;
+        }
+        let mut positions = [position.clone()];
+        let mut velocities = [velocity.clone()];
+        integrate(physics.integrator, &mut positions, &mut velocities, delta, |positions| {
+            positions.iter().map(|position| acceleration(physics.gravity, position, planets)).collect()
+        });
+        let [new_position] = positions;
+        let [new_velocity] = velocities;
+        *position = new_position;
+        *velocity = new_velocity;
+    }
+}
+
+/// Moves all celestial bodies with a velocity together, each one pulled by every other celestial body
+fn integrate_nbody(physics: &Physics, world: &mut World, delta: f64) {
+    let fixed: Vec<(Position, Celestial)> = world.query::<Without<Velocity, (&Position, &Celestial)>>().iter().map(|(_id, (pos, cel))|{
+        (pos.clone(), cel.clone())
+    }).collect();
+    let moving: Vec<(Entity, Position, Velocity, Celestial)> = world.query::<(&Position, &Velocity, &Celestial)>().iter().map(|(id, (pos, vel, cel))|{
+        (id, pos.clone(), vel.clone(), cel.clone())
+    }).collect();
+    let mut positions: Vec<Position> = moving.iter().map(|(_id, pos, _vel, _cel)| pos.clone()).collect();
+    let mut velocities: Vec<Velocity> = moving.iter().map(|(_id, _pos, vel, _cel)| vel.clone()).collect();
+
+    integrate(physics.integrator, &mut positions, &mut velocities, delta, |positions| {
+        positions.iter().enumerate().map(|(i, position)| {
+            let (fx, fy) = acceleration(physics.gravity, position, &fixed);
+            let others: Vec<(Position, Celestial)> = positions.iter().zip(&moving).enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_j, (other, (_id, _pos, _vel, celestial)))| (other.clone(), celestial.clone()))
+                .collect();
+            let (mx, my) = acceleration(physics.gravity, position, &others);
+            (fx + mx, fy + my)
+        }).collect()
+    });
+
+    for ((id, _pos, _vel, _cel), (position, velocity)) in moving.iter().zip(positions.into_iter().zip(velocities)) {
+        if let Ok(mut p) = world.get_mut::<Position>(*id) {
+            *p = position;
+        }
+        if let Ok(mut v) = world.get_mut::<Velocity>(*id) {
+            *v = velocity;
+        }
+    }
+}
+
 pub fn system_gravity(world: &mut World, delta: f64) {
+    let physics = world.query::<&Physics>().iter().next().map(|(_id, physics)| physics.clone()).unwrap_or_default();
     let planets: Vec<(Position, Celestial)> = world.query::<(&Position, &Celestial)>().iter().map(|(_id, (pos, cel))|{
         (pos.clone(), cel.clone())
     }).collect();
-    
-    for (_id, (position, velocity)) in &mut world.query::<(&mut Position, &mut Velocity)>() {
-        for (celestial_position, celestial) in &planets {
-            velocity.x += (celestial_position.x-position.x) * delta * celestial.mass;
-            velocity.y += (celestial_position.y-position.y) * delta * celestial.mass;
-        }
-        // Roce
-        //velocity.x *= 0.99;
-        //velocity.y *= 0.99;
-        position.x += velocity.x * delta;
-        position.y += velocity.y * delta;
-    }
-}
-
-pub fn system_crash(world: &mut World) {
-    for (_id, spaceship_position) in &mut world.query::<With<SpaceShip, &Position>>() {
-        for (_id, (position, celestial)) in &mut world.query::<(&Position, &Celestial)>() {
-            if ((position.x - spaceship_position.x).powf(2.0) + (position.y - spaceship_position.y).powf(2.0)).sqrt() < celestial.radius {
-                for (_id, finish) in &mut world.query::<&mut Finish>(){
-                    finish.crash = true;
+
+    integrate_free(&physics, world, delta, &planets);
+    if physics.nbody {
+        integrate_nbody(&physics, world, delta);
+    }
+}
+
+pub fn system_orbit(world: &mut World, delta: f64) {
+    let offsets: HashMap<Entity, (Entity, Position)> = world.query::<&mut Orbit>().iter().map(|(id, orbit)| {
+        orbit.time += delta;
+        (id, (orbit.parent, orbit.offset()))
+    }).collect();
+
+    for id in offsets.keys() {
+        if let Some(new_position) = chained_position(*id, world, &offsets) {
+            if let Ok(mut position) = world.get_mut::<Position>(*id) {
+                *position = new_position;
+            }
+        }
+    }
+}
+
+pub fn system_drag(world: &mut World, delta: f64) {
+    let mut atmospheres = Vec::new();
+    for (_id, (atmosphere, position)) in &mut world.query::<(&Atmosphere, &Position)>() {
+        let (vx, vy) = world.get::<Velocity>(atmosphere.planet).map(|velocity| (velocity.x, velocity.y)).unwrap_or((0.0, 0.0));
+        atmospheres.push((position.clone(), vx, vy, atmosphere.radius, atmosphere.density));
+    }
+
+    for (_id, (position, velocity)) in &mut world.query::<Without<Celestial, (&Position, &mut Velocity)>>() {
+        for (center, vx, vy, radius, density) in &atmospheres {
+            if ((center.x - position.x).powi(2) + (center.y - position.y).powi(2)).sqrt() < *radius {
+                // Drag proportional to the speed relative to the air, solved exactly for the step
+                let damping = (-density * delta).exp();
+                velocity.x = vx + (velocity.x - vx) * damping;
+                velocity.y = vy + (velocity.y - vy) * damping;
+            }
+        }
+    }
+}
+
+pub fn system_crash(world: &mut World, delta: f64) {
+    if world.query::<&Finish>().iter().any(|(_id, finish)| finish.finish || finish.crash) {
+        return;
+    }
+    let mut first: Option<(Entity, Entity, Impact)> = None;
+    for (id, (spaceship_position, spaceship_previous)) in &mut world.query::<Without<Landed, With<SpaceShip, (&Position, Option<&PreviousPosition>)>>>() {
+        let spaceship_previous = spaceship_previous.map(|p| Position { x: p.x, y: p.y }).unwrap_or_else(|| spaceship_position.clone());
+        for (planet, (position, previous, celestial)) in &mut world.query::<(&Position, Option<&PreviousPosition>, &Celestial)>() {
+            let previous = previous.map(|p| Position { x: p.x, y: p.y }).unwrap_or_else(|| position.clone());
+            // Test the movement relative to the planet, as both may have moved during the step
+            let from = Position {
+                x: spaceship_previous.x - previous.x,
+                y: spaceship_previous.y - previous.y,
+            };
+            let to = Position {
+                x: spaceship_position.x - position.x,
+                y: spaceship_position.y - position.y,
+            };
+            if let Some(time) = segment_circle(&from, &to, &Position { x: 0.0, y: 0.0 }, celestial.radius) {
+                if first.as_ref().is_none_or(|(_id, _planet, impact)| time < impact.time) {
+                    let point = lerp(&spaceship_previous, spaceship_position, time);
+                    first = Some((id, planet, Impact { x: point.x, y: point.y, time }));
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    if let Some((id, planet, impact)) = first {
+        if touchdown(world, id, planet, &impact, delta) {
+            return;
+        }
+        for (_id, finish) in &mut world.query::<&mut Finish>(){
+            finish.crash = true;
+            finish.impact = Some(impact.clone());
+        }
+        if let Ok(mut position) = world.get_mut::<Position>(id) {
+            position.x = impact.x;
+            position.y = impact.y;
+        }
+        let _ = world.remove_one::<Velocity>(id);
+    }
+}
+
+pub fn system_reach(world: &mut World, delta: f64) {
+    if world.query::<&Finish>().iter().any(|(_id, finish)| finish.finish || finish.reached) {
+        return;
+    }
+    for (_id, (spaceship, spaceship_position, spaceship_previous, spaceship_velocity)) in &mut world.query::<(&SpaceShip, &Position, Option<&PreviousPosition>, Option<&Velocity>)>() {
+        let spaceship_velocity = spaceship_velocity.map(|v| (v.x, v.y)).unwrap_or((0.0, 0.0));
+        let spaceship_previous = spaceship_previous.map(|p| Position { x: p.x, y: p.y }).unwrap_or_else(|| spaceship_position.clone());
+        for (_id, (zone, zone_position, zone_previous, dock)) in &mut world.query::<(&mut EndZone, &Position, Option<&PreviousPosition>, Option<&mut Dock>)>() {
+            zone.inside = false;
+            let docked = match dock {
+                Some(dock) => {
+                    let zone_velocity = match zone_previous {
+                        Some(previous) if delta > 0.0 => ((zone_position.x - previous.x) / delta, (zone_position.y - previous.y) / delta),
+                        _ => (0.0, 0.0),
+                    };
+                    dock.check(spaceship_velocity, zone_velocity)
+                },
+                None => true,
+            };
+            if let Some(planet) = zone.visit {
+                if !spaceship.visited.contains(&planet) {
+                    continue;
+                }
+            }
+            let margin = zone.margin;
+            let hit = match zone.shape {
+                ZoneShape::Rect { width, height } => {
+                    let min = Position {
+                        x: zone_position.x + margin,
+                        y: zone_position.y + margin,
+                    };
+                    let max = Position {
+                        x: zone_position.x + width - margin,
+                        y: zone_position.y + height - margin,
+                    };
+                    segment_rect(&spaceship_previous, spaceship_position, &min, &max)
+                },
+                ZoneShape::Circle { radius } => segment_circle(&spaceship_previous, spaceship_position, zone_position, radius - margin),
+                ZoneShape::Annulus { inner, outer, .. } => segment_annulus(&spaceship_previous, spaceship_position, zone_position, inner + margin, outer - margin),
+            };
+            zone.inside = hit.is_some() && docked;
+        }
+    }
+}
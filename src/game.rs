@@ -0,0 +1,410 @@
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use instant::Instant;
+use hecs::World;
+
+use crate::{Outcome, ImageStore, system_finish};
+use crate::levels::Levels;
+use crate::input::Input;
+use crate::hud::system_hud;
+use crate::minimap::system_minimap;
+use crate::renderer::{ZOOM_STEP, system_offset, system_renderer, system_zoom};
+use crate::backend::Backend;
+use crate::canvas::Canvas;
+#[cfg(feature = "webgl")]
+use crate::webgl::WebGl;
+use crate::simulation::{level_over, system_step};
+use crate::replay::{Recording, ending};
+use crate::timestep::{Timestep, PHYSICS_RATE, MAX_SUBSTEPS};
+use crate::trajectory::system_trajectory;
+use crate::web::*;
+
+const KEY_W: u32 = 87;
+const KEY_S: u32 = 83;
+const KEY_C: u32 = 67;
+const ORBITAL_BUS_LEVEL: &str = "orbital-bus-level";
+const ORBITAL_BUS_MAX_LEVEL: &str = "orbital-bus-max-level";
+const ORBITAL_BUS_REPLAY: &str = "orbital-bus-replay";
+/// List of level pack files served next to game.html
+const PACKS_MANIFEST: &str = "packs.ron";
+
+/// Source of the input of every physics step: the player, recorded as they go, or a previous recording
+pub enum Replay {
+    Record(Recording),
+    Play(Recording, usize),
+}
+
+impl Replay {
+    fn recording(&self) -> &Recording {
+        match self {
+            Replay::Record(recording) => recording,
+            Replay::Play(recording, _step) => recording,
+        }
+    }
+
+    fn next(&mut self, input: &Input) -> Input {
+        match self {
+            Replay::Record(recording) => {
+                recording.push(input);
+                Input {
+                    click: false,
+                    ..input.clone()
+                }
+            },
+            Replay::Play(recording, step) => {
+                *step += 1;
+                recording.input(*step - 1)
+            },
+        }
+    }
+}
+
+/// Where frames are drawn
+enum Screen {
+    Canvas(web_sys::CanvasRenderingContext2d),
+    #[cfg(feature = "webgl")]
+    WebGl(Box<WebGl>),
+}
+
+impl Screen {
+    /// WebGL2 is used when built with it and asked for with `?webgl`. A canvas keeps the first kind of
+    /// context it is asked for, so if WebGL2 fails after creating its context the canvas is replaced
+    /// by a fresh copy for Canvas2D
+    fn new(canvas: web_sys::HtmlCanvasElement) -> (web_sys::HtmlCanvasElement, Screen) {
+        #[cfg(feature = "webgl")]
+        let canvas = if window().location().search().unwrap_or_default().contains("webgl") {
+            if let Some(webgl) = WebGl::new(&canvas) {
+                log("Renderer: WebGL2");
+                return (canvas, Screen::WebGl(Box::new(webgl)));
+            }
+            log("WebGL2 is not available, falling back to Canvas2D");
+            let fresh = canvas.clone_node().unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+            canvas.replace_with_with_node_1(&fresh).unwrap();
+            fresh
+        } else {
+            canvas
+        };
+        let context = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .unwrap();
+        (canvas, Screen::Canvas(context))
+    }
+
+    fn draw(&mut self, world: &mut World, store: &ImageStore, alpha: f64) {
+        match self {
+            Screen::Canvas(context) => {
+                let mut canvas = Canvas::new(context, store);
+                canvas.clear();
+                system_renderer(world, &mut canvas, alpha);
+            },
+            #[cfg(feature = "webgl")]
+            Screen::WebGl(webgl) => {
+                webgl.upload_images(store);
+                system_renderer(world, &mut webgl.batch, alpha);
+                webgl.draw();
+            },
+        }
+    }
+}
+
+/// Adds the packs listed in the manifest on top of the built-in levels, later ones on top of earlier ones. Broken packs are skipped
+async fn load_packs() -> Levels {
+    let mut levels = Levels::builtin();
+    let files: Vec<String> = fetch_text(PACKS_MANIFEST).await
+        .and_then(|text| ron::from_str(&text).ok())
+        .unwrap_or_default();
+    for file in files {
+        match fetch_text(&file).await.map(|text| levels.add_pack(&text)) {
+            Some(Ok(())) => (),
+            Some(Err(error)) => log(&format!("Level pack {} is broken: {}", file, error)),
+            None => log(&format!("Level pack {} not found", file)),
+        }
+    }
+    for pack in levels.packs() {
+        log(&format!("Level pack: {}", pack));
+    }
+    levels
+}
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    log("Welcome to Orbital Bus!");
+    wasm_bindgen_futures::spawn_local(async {
+        let levels = load_packs().await;
+        play(levels);
+    });
+}
+
+/// Sets up the level and the input handlers, then starts the game loop
+fn play(levels: Levels) {
+    let canvas = document().get_element_by_id("canvas").unwrap();
+    let canvas: web_sys::HtmlCanvasElement = canvas
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .map_err(|_| ())
+        .unwrap();
+
+    let (canvas, screen) = Screen::new(canvas);
+
+    let mut store = HashMap::new();
+    let playback = window().location().search().unwrap_or_default().contains("replay");
+    let replay = get_local_storage_item(ORBITAL_BUS_REPLAY)
+        .filter(|_| playback)
+        .and_then(|text| Recording::decode(&text))
+        .map(|recording| Replay::Play(recording, 0))
+        .unwrap_or_else(|| {
+            let level: i32 = get_local_storage(ORBITAL_BUS_LEVEL).parse().expect("orbital-bus-level is not a number");
+            Replay::Record(Recording::new(level, PHYSICS_RATE))
+        });
+    let world = levels.load_level(replay.recording().level, &mut store);
+
+    let input = Rc::new(RefCell::new(Input::default()));
+    let input_handler = input.clone();
+    let click_handler = Closure::wrap(Box::new(move |event: web_sys::MouseEvent|{
+        let mut input = input_handler.borrow_mut();
+        let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let (x, y) = get_position(canvas, event);
+        if y > 10.0 && y < 210.0 {
+            if x > 10.0 && x < 110.0 {
+                go_web("index.html");
+            }
+            if x > 110.0 && x < 210.0 {
+                window().location().reload().unwrap();
+            }
+        } else {
+            input.click = true;
+        }
+    }) as Box<dyn Fn(_)>);
+    // Last mouse position while dragging the free camera
+    let drag: Rc<Cell<Option<(f64, f64)>>> = Rc::new(Cell::new(None));
+    let input_handler = input.clone();
+    let drag_handler = drag.clone();
+    let down_handler = Closure::wrap(Box::new(move |event|{
+        let mut input = input_handler.borrow_mut();
+        let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let (x, y) = get_position(canvas, event);
+        if x > 10.0 && x < 260.0 && y > 500.0 {
+            if y > 500.0 && y < 692.0 {
+                input.forward = true;
+            }
+            if y > 692.0 && y < 784.0 {
+                input.brake = true;
+            }
+        } else {
+            drag_handler.set(Some((x, y)));
+        }
+    }) as Box<dyn Fn(_)>);
+    let input_handler = input.clone();
+    let drag_handler = drag.clone();
+    let move_handler = Closure::wrap(Box::new(move |event|{
+        if let Some((last_x, last_y)) = drag_handler.get() {
+            let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+            let (x, y) = get_position(canvas, event);
+            let mut input = input_handler.borrow_mut();
+            input.pan.0 += x - last_x;
+            input.pan.1 += y - last_y;
+            drag_handler.set(Some((x, y)));
+        }
+    }) as Box<dyn Fn(_)>);
+    let input_handler = input.clone();
+    let drag_handler = drag.clone();
+    let up_handler = Closure::wrap(Box::new(move |event|{
+        drag_handler.set(None);
+        let mut input = input_handler.borrow_mut();
+        let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let (x, y) = get_position(canvas, event);
+        if x > 10.0 && x < 260.0 {
+            if y > 500.0 && y < 692.0 {
+                input.forward = false;
+            }
+            if y > 692.0 && y < 784.0 {
+                input.brake = false;
+            }
+        }
+    }) as Box<dyn Fn(_)>);
+
+    let input_handler = input.clone();
+    let drag_handler = drag.clone();
+    let touchstart_handler = Closure::wrap(Box::new(move |event: web_sys::TouchEvent|{
+        let mut input = input_handler.borrow_mut();
+        let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let fingers = event.touches().length();
+        let (x, y) = get_touch_position(canvas, event);
+        if x > 10.0 && x < 260.0 && y > 500.0 {
+            if y > 500.0 && y < 692.0 {
+                input.forward = true;
+            }
+            if y > 692.0 && y < 784.0 {
+                input.brake = true;
+            }
+        } else if fingers == 1 {
+            // One finger drags the free camera, a second one turns it into a pinch
+            drag_handler.set(Some((x, y)));
+        } else {
+            drag_handler.set(None);
+        }
+    }) as Box<dyn Fn(_)>);
+
+    // Distance between the two fingers of a pinch at the previous touchmove
+    let pinch: Rc<Cell<Option<f64>>> = Rc::new(Cell::new(None));
+    let input_handler = input.clone();
+    let pinch_handler = pinch.clone();
+    let drag_handler = drag.clone();
+    let touchmove_handler = Closure::wrap(Box::new(move |event: web_sys::TouchEvent|{
+        let touches = event.touches();
+        if let (Some(first), Some(second)) = (touches.get(0), touches.get(1)) {
+            event.prevent_default();
+            let distance = ((first.page_x() - second.page_x()) as f64).hypot((first.page_y() - second.page_y()) as f64);
+            if let Some(previous) = pinch_handler.get() {
+                input_handler.borrow_mut().zoom += (distance / previous).ln() / ZOOM_STEP.ln();
+            }
+            pinch_handler.set(Some(distance));
+        } else if let Some((last_x, last_y)) = drag_handler.get() {
+            event.prevent_default();
+            let canvas = document().get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+            let (x, y) = get_touch_position(canvas, event);
+            let mut input = input_handler.borrow_mut();
+            input.pan.0 += x - last_x;
+            input.pan.1 += y - last_y;
+            drag_handler.set(Some((x, y)));
+        }
+    }) as Box<dyn Fn(_)>);
+
+    let input_handler = input.clone();
+    let touchend_handler = Closure::wrap(Box::new(move ||{
+        let mut input = input_handler.borrow_mut();
+        input.forward = false;
+        input.brake = false;
+        pinch.set(None);
+        drag.set(None);
+    }) as Box<dyn Fn()>);
+
+    let input_handler = input.clone();
+    let wheel_handler = Closure::wrap(Box::new(move |event: web_sys::WheelEvent|{
+        event.prevent_default();
+        // Horizontal scrolling sends events with no vertical delta, whose signum would still be 1
+        if event.delta_y() != 0.0 {
+            input_handler.borrow_mut().zoom -= event.delta_y().signum();
+        }
+    }) as Box<dyn Fn(_)>);
+
+    let contextmenu_handler = Closure::wrap(Box::new(move |event: web_sys::Event|{
+        event.prevent_default();
+        event.stop_propagation();
+    }) as Box<dyn Fn(_)>);
+
+    let input_handler = input.clone();
+    let keydown_handler = Closure::wrap(Box::new(move |event: web_sys::Event|{
+        let mut input = input_handler.borrow_mut();
+        let event = wasm_bindgen::JsCast::dyn_ref::<web_sys::KeyboardEvent>(&event).unwrap();
+        input.keys.push(event.key_code());
+        match event.key_code() {
+            KEY_W => input.forward = true,
+            KEY_S => input.brake = true,
+            KEY_C => input.camera = true,
+            _ => ()
+        };
+    }) as Box<dyn Fn(_)>);
+
+    let input_handler = input.clone();
+    let keyup_handler = Closure::wrap(Box::new(move |event: web_sys::Event|{
+        let mut input = input_handler.borrow_mut();
+        let event = wasm_bindgen::JsCast::dyn_ref::<web_sys::KeyboardEvent>(&event).unwrap();
+        match event.key_code() {
+            KEY_W => input.forward = false,
+            KEY_S => input.brake = false,
+            _ => ()
+        };
+    }) as Box<dyn Fn(_)>);
+
+    window().set_onclick(Some(click_handler.as_ref().unchecked_ref()));
+    window().set_onkeydown(Some(keydown_handler.as_ref().unchecked_ref()));
+    window().set_onkeyup(Some(keyup_handler.as_ref().unchecked_ref()));
+    canvas.set_onmousedown(Some(down_handler.as_ref().unchecked_ref()));
+    canvas.set_onmousemove(Some(move_handler.as_ref().unchecked_ref()));
+    canvas.set_onmouseup(Some(up_handler.as_ref().unchecked_ref()));
+    canvas.set_ontouchstart(Some(touchstart_handler.as_ref().unchecked_ref()));
+    canvas.set_ontouchmove(Some(touchmove_handler.as_ref().unchecked_ref()));
+    canvas.set_ontouchend(Some(touchend_handler.as_ref().unchecked_ref()));
+    canvas.set_onwheel(Some(wheel_handler.as_ref().unchecked_ref()));
+    canvas.set_oncontextmenu(Some(contextmenu_handler.as_ref().unchecked_ref()));
+
+    click_handler.forget();
+    keydown_handler.forget();
+    keyup_handler.forget();
+    down_handler.forget();
+    move_handler.forget();
+    up_handler.forget();
+    touchstart_handler.forget();
+    touchmove_handler.forget();
+    touchend_handler.forget();
+    wheel_handler.forget();
+    contextmenu_handler.forget();
+
+    let ginput = input.clone();
+    let now = Instant::now();
+    let timestep = Timestep::new(replay.recording().rate, MAX_SUBSTEPS);
+    request_animation_frame(move ||{
+        gloop(screen, world, ginput, store, timestep, replay, now);
+    });
+}
+
+fn gloop(screen: Screen, world: World, input: Rc<RefCell<Input>>, store: ImageStore, timestep: Timestep, replay: Replay, prev: Instant){
+    let mut world = world;
+    let mut screen = screen;
+    let mut timestep = timestep;
+    let mut replay = replay;
+    let now = Instant::now();
+    let delta = now.duration_since(prev).as_secs_f64();
+    {
+        let mut input = input.borrow_mut();
+
+        let steps = timestep.advance(delta);
+        for _ in 0..steps {
+            if level_over(&world) {
+                break;
+            }
+            let step_input = replay.next(&input);
+            system_step(&mut world, &step_input, timestep.step);
+        }
+        if let Replay::Record(recording) = &mut replay {
+            if recording.ending.is_none() {
+                recording.ending = ending(&world);
+                if recording.ending.is_some() {
+                    set_local_storage(ORBITAL_BUS_REPLAY, &recording.encode());
+                }
+            }
+        }
+        match system_finish(&mut world, &mut input) {
+            Outcome::NextLevel => {
+                let level: i32 = get_local_storage(ORBITAL_BUS_LEVEL).parse().unwrap();
+                let max_level: i32 = get_local_storage(ORBITAL_BUS_MAX_LEVEL).parse().unwrap();
+                // Watching a replay does not unlock levels
+                let playing = matches!(replay, Replay::Record(_));
+                if playing && max_level == level {
+                    let new_level = format!("{}", level + 1);
+                    set_local_storage(ORBITAL_BUS_MAX_LEVEL, &new_level);
+                }
+                go_web("index.html");
+            },
+            Outcome::Restart => window().location().reload().unwrap(),
+            Outcome::Playing => (),
+        }
+        system_trajectory(&mut world, timestep.step);
+        system_hud(&mut world);
+        system_minimap(&mut world, &input);
+        input.keys.clear();
+        system_zoom(&mut world, &mut input);
+        system_offset(&mut world, &mut input, delta, timestep.alpha());
+        screen.draw(&mut world, &store, timestep.alpha());
+    }
+    request_animation_frame(move ||{
+        gloop(screen, world, input, store, timestep, replay, now);
+    });
+}
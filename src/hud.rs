@@ -1,11 +1,26 @@
 use hecs::{With, World};
 
-use crate::{SpaceShip, Finish, ImageStore};
+use crate::{SpaceShip, EndZone, Finish, ImageStore};
+use crate::docking::Dock;
+use crate::passengers::count;
 use crate::renderer::{Renderer, Camera};
 use crate::dynamics::Position;
+use crate::minimap::Minimap;
+
+/// The docking readout is only shown this close to the zone
+const DOCK_READOUT_DISTANCE: f64 = 300.0;
 
 pub struct FuelHUD;
 
+/// Relative speed and approach angle to a docking zone
+pub struct DockHUD;
+
+/// Passengers aboard and delivered
+pub struct PassengerHUD;
+
+/// Current camera mode and the key that changes it
+pub struct CameraHUD;
+
 pub fn system_hud(world: &mut World) {
     for (_id, spaceship) in &mut world.query::<&SpaceShip>() {
         for (_id, (renderer, position)) in &mut world.query::<With<FuelHUD, (&mut Renderer, &mut Position)>>(){
@@ -15,11 +30,44 @@ pub fn system_hud(world: &mut World) {
             }      
         }
     }
+    for (_id, camera) in &mut world.query::<&Camera>() {
+        for (_id, renderer) in &mut world.query::<With<CameraHUD, &mut Renderer>>() {
+            if let Renderer::TextRenderer{text, ..} = renderer {
+                *text = format!("Camera {} (C)", camera.mode.name());
+            }
+        }
+    }
+    let (aboard, delivered, total) = count(world);
+    for (_id, spaceship) in &mut world.query::<&SpaceShip>() {
+        for (_id, renderer) in &mut world.query::<With<PassengerHUD, &mut Renderer>>() {
+            if let Renderer::TextRenderer{text, ..} = renderer {
+                *text = format!("Aboard {} / {}  Delivered {} / {}", aboard, spaceship.capacity, delivered, total);
+            }
+        }
+    }
+    for (_id, (_spaceship, ship_position)) in &mut world.query::<(&SpaceShip, &Position)>() {
+        for (_id, (zone, zone_position, dock)) in &mut world.query::<(&EndZone, &Position, &Dock)>() {
+            for (_id, renderer) in &mut world.query::<With<DockHUD, &mut Renderer>>() {
+                if let Renderer::TextRenderer{text, color, ..} = renderer {
+                    if zone.distance(zone_position, ship_position) < DOCK_READOUT_DISTANCE {
+                        let mut readout = format!("Relative speed {:.0} / {:.0}", dock.relative_speed, dock.max_speed);
+                        if let Some(max_angle) = dock.max_angle {
+                            readout.push_str(&format!("  Angle {:.0} / {:.0}", dock.deviation().to_degrees(), max_angle.to_degrees()));
+                        }
+                        *text = readout;
+                        *color = if dock.relative_speed <= dock.max_speed { "#7efa37".to_string() } else { "white".to_string() };
+                    } else {
+                        text.clear();
+                    }
+                }
+            }
+        }
+    }
 }
 
-pub fn build_hud(world: &mut World, mut store: &mut ImageStore) {
+pub fn build_hud(world: &mut World, store: &mut ImageStore) {
     {
-        let mut renderer = Renderer::sprite("hud.png".to_string(), &mut store);
+        let mut renderer = Renderer::sprite("hud.png".to_string(), store);
         renderer.set_fixed(true);
         renderer.set_z(8);
         let position = Position {
@@ -28,7 +76,7 @@ pub fn build_hud(world: &mut World, mut store: &mut ImageStore) {
         };
         world.spawn((renderer, position));
 
-        let mut renderer = Renderer::sprite("can.png".to_string(), &mut store);
+        let mut renderer = Renderer::sprite("can.png".to_string(), store);
         renderer.set_fixed(true);
         renderer.set_z(10);
         let position = Position {
@@ -47,7 +95,7 @@ pub fn build_hud(world: &mut World, mut store: &mut ImageStore) {
         world.spawn((renderer, position, FuelHUD));
     }
     {
-        let mut renderer = Renderer::sprite("controls.png".to_string(), &mut store);
+        let mut renderer = Renderer::sprite("controls.png".to_string(), store);
         renderer.set_fixed(true);
         renderer.set_z(10);
         let position = Position {
@@ -57,7 +105,7 @@ pub fn build_hud(world: &mut World, mut store: &mut ImageStore) {
         world.spawn((renderer, position));
     }
     {
-        let mut renderer = Renderer::sprite("back-restart.png".to_string(), &mut store);
+        let mut renderer = Renderer::sprite("back-restart.png".to_string(), store);
         renderer.set_fixed(true);
         renderer.set_z(10);
         let position = Position {
@@ -67,13 +115,18 @@ pub fn build_hud(world: &mut World, mut store: &mut ImageStore) {
         world.spawn((renderer, position));
     }
     {
-        let camera = Camera {
-            offset: Position {
-                x: 0.0,
-                y: 0.0,
-            }
+        let mut renderer = Renderer::text(String::new(), "white".to_string(), "20px Tsoonami".to_string());
+        renderer.set_fixed(true);
+        renderer.set_z(10);
+        let position = Position {
+            x: 20.0,
+            y: 240.0,
         };
-        world.spawn((camera,));
+        world.spawn((renderer, position, CameraHUD));
+    }
+    {
+        world.spawn((Camera::default(),));
+        world.spawn((Minimap::default(),));
     }
     {
         let finish = Finish::default();
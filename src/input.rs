@@ -1,21 +1,30 @@
-use hecs::World;
+use hecs::{Without, World};
 
 use crate::SpaceShip;
 use crate::dynamics::Velocity;
+use crate::landing::Landed;
 
 const MAX_SPEED: f64 = 1000.0;
 const SPEED_INCREMENT: f64 = 1000.0;
 const SPEED_DECREMENT: f64 = 600.0;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Input {
     pub forward: bool,
     pub brake: bool,
     pub click: bool,
+    /// Zoom steps requested since the last frame by the wheel or a pinch, positive zooms in
+    pub zoom: f64,
+    /// Screen distance dragged since the last frame, moves the free camera
+    pub pan: (f64, f64),
+    /// Switch to the next camera mode
+    pub camera: bool,
+    /// Key codes pressed since the last frame
+    pub keys: Vec<u32>,
 }
 
 pub fn system_spacecraft_input(world: &mut World, input: &Input, delta: f64) {
-    for (_id, (spaceship, velocity)) in &mut world.query::<(&mut SpaceShip, &mut Velocity)>() {
+    for (_id, (spaceship, velocity)) in &mut world.query::<Without<Landed, (&mut SpaceShip, &mut Velocity)>>() {
         spaceship.angle = velocity.y.atan2(velocity.x);
         let mut x = velocity.x;
         let mut y = velocity.y;
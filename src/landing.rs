@@ -0,0 +1,123 @@
+use hecs::{Entity, World};
+
+use crate::SpaceShip;
+use crate::collision::Impact;
+use crate::dynamics::*;
+use crate::input::Input;
+
+const TAKEOFF_SPEED: f64 = 150.0;
+const TAKEOFF_FUEL: f64 = 1.0;
+const TAKEOFF_HEIGHT: f64 = 2.0;
+
+/// Per level limits for a soft landing. Without them any contact with a planet is a crash
+#[derive(Clone)]
+pub struct LandingRules {
+    pub max_speed: f64,
+    /// Maximum deviation of the approach from the vertical, in radians
+    pub max_angle: f64,
+    pub refuel: bool,
+}
+
+/// Spaceship parked on the surface of a planet, at `angle` from its centre
+#[derive(Clone)]
+pub struct Landed {
+    pub planet: Entity,
+    pub angle: f64,
+}
+
+fn planet_velocity(world: &World, planet: Entity, delta: f64) -> (f64, f64) {
+    if let Ok(velocity) = world.get::<Velocity>(planet) {
+        return (velocity.x, velocity.y);
+    }
+    match (world.get::<Position>(planet), world.get::<PreviousPosition>(planet)) {
+        (Ok(position), Ok(previous)) if delta > 0.0 => ((position.x - previous.x) / delta, (position.y - previous.y) / delta),
+        _ => (0.0, 0.0),
+    }
+}
+
+fn planet_surface(world: &World, planet: Entity) -> Option<(Position, f64)> {
+    let position = world.get::<Position>(planet).ok()?;
+    let celestial = world.get::<Celestial>(planet).ok()?;
+    Some(((*position).clone(), celestial.radius))
+}
+
+/// Parks the spaceship on the planet if the contact is gentle enough. Returns false if it is a crash
+pub fn touchdown(world: &mut World, ship: Entity, planet: Entity, impact: &Impact, delta: f64) -> bool {
+    let rules = match world.query::<&LandingRules>().iter().next() {
+        Some((_id, rules)) => rules.clone(),
+        None => return false,
+    };
+    let (center, radius) = match planet_surface(world, planet) {
+        Some(surface) => surface,
+        None => return false,
+    };
+    let (pvx, pvy) = planet_velocity(world, planet, delta);
+    let (vx, vy) = match world.get::<Velocity>(ship) {
+        Ok(velocity) => (velocity.x - pvx, velocity.y - pvy),
+        Err(_) => return false,
+    };
+    let speed = (vx * vx + vy * vy).sqrt();
+    let normal = (impact.y - center.y).atan2(impact.x - center.x);
+    let inward = normal + std::f64::consts::PI;
+    let deviation = (vy.atan2(vx) - inward).sin().atan2((vy.atan2(vx) - inward).cos()).abs();
+    if speed > rules.max_speed || (speed > 0.0 && deviation > rules.max_angle) {
+        return false;
+    }
+
+    if let Ok(mut spaceship) = world.get_mut::<SpaceShip>(ship) {
+        spaceship.angle = normal;
+        if rules.refuel {
+            spaceship.fuel = spaceship.initial_fuel;
+        }
+        if !spaceship.visited.contains(&planet) {
+            spaceship.visited.push(planet);
+        }
+    }
+    // Rest on the surface, moving along with the planet
+    if let Ok(mut position) = world.get_mut::<Position>(ship) {
+        position.x = center.x + radius * normal.cos();
+        position.y = center.y + radius * normal.sin();
+    }
+    if let Ok(mut velocity) = world.get_mut::<Velocity>(ship) {
+        velocity.x = pvx;
+        velocity.y = pvy;
+    }
+    world.insert_one(ship, Landed { planet, angle: normal }).unwrap();
+    true
+}
+
+/// Keeps landed spaceships on the surface of their planet and lets them take off when thrusting
+/// with at least `TAKEOFF_FUEL` left
+pub fn system_landed(world: &mut World, input: &Input, delta: f64) {
+    let landed: Vec<(Entity, Landed)> = world.query::<&Landed>().iter().map(|(id, landed)| (id, landed.clone())).collect();
+    for (id, landed) in landed {
+        let (center, radius) = match planet_surface(world, landed.planet) {
+            Some(surface) => surface,
+            None => continue,
+        };
+        let (pvx, pvy) = planet_velocity(world, landed.planet, delta);
+        let (cos, sin) = (landed.angle.cos(), landed.angle.sin());
+        let takeoff = input.forward && world.get::<SpaceShip>(id).map(|spaceship| spaceship.fuel >= TAKEOFF_FUEL).unwrap_or(false);
+        let height = if takeoff { radius + TAKEOFF_HEIGHT } else { radius };
+        let speed = if takeoff { TAKEOFF_SPEED } else { 0.0 };
+
+        if let Ok(mut position) = world.get_mut::<Position>(id) {
+            position.x = center.x + height * cos;
+            position.y = center.y + height * sin;
+        }
+        if let Ok(mut velocity) = world.get_mut::<Velocity>(id) {
+            velocity.x = pvx + speed * cos;
+            velocity.y = pvy + speed * sin;
+        }
+        if takeoff {
+            if let Ok(mut previous) = world.get_mut::<PreviousPosition>(id) {
+                previous.x = center.x + height * cos;
+                previous.y = center.y + height * sin;
+            }
+            if let Ok(mut spaceship) = world.get_mut::<SpaceShip>(id) {
+                spaceship.fuel -= TAKEOFF_FUEL;
+            }
+            world.remove_one::<Landed>(id).unwrap();
+        }
+    }
+}
@@ -1,9 +1,15 @@
-use crate::{ImageStore, SpaceShip, EndZone};
-use crate::dynamics::{Celestial, Position, Velocity};
+use crate::{ImageStore, SpaceShip, EndZone, ZoneShape};
+use hecs::Entity;
+
+use crate::dynamics::{Atmosphere, Celestial, Position, PreviousPosition, Velocity};
+use crate::hud::{DockHUD, PassengerHUD};
+use crate::passengers::{Passenger, Stop, Trip};
 use crate::renderer::{Renderer};
+use crate::trajectory::Trajectory;
+use crate::transform::Attached;
 
-pub fn background(mut store: &mut ImageStore) -> (Renderer, Position) {
-    let mut renderer = Renderer::sprite("space.png".to_string(), &mut store);
+pub fn background(store: &mut ImageStore) -> (Renderer, Position) {
+    let mut renderer = Renderer::sprite("space.png".to_string(), store);
     renderer.set_z(-100);
     renderer.set_fixed(true);
     let position = Position {
@@ -13,12 +19,16 @@ pub fn background(mut store: &mut ImageStore) -> (Renderer, Position) {
     (renderer, position)
 }
 
-pub fn spaceship(x: f64, y: f64, vx: f64, vy: f64, fuel: f64, mut store: &mut ImageStore) -> (Renderer, Position, Velocity, SpaceShip) {
-    let renderer = Renderer::sprite("spaceship.png".to_string(), &mut store);
+pub fn spaceship(x: f64, y: f64, vx: f64, vy: f64, fuel: f64, capacity: u32, store: &mut ImageStore) -> (Renderer, Position, PreviousPosition, Velocity, SpaceShip) {
+    let renderer = Renderer::sprite("spaceship.png".to_string(), store);
     let position = Position {
         x,
         y,
     };
+    let previous = PreviousPosition {
+        x,
+        y,
+    };
     let velocity = Velocity {
         x: vx,
         y: vy
@@ -26,13 +36,15 @@ pub fn spaceship(x: f64, y: f64, vx: f64, vy: f64, fuel: f64, mut store: &mut Im
     let spaceship = SpaceShip {
         angle: 0.0,
         fuel,
-        initial_fuel: fuel
+        initial_fuel: fuel,
+        visited: Vec::new(),
+        capacity,
     };
-    (renderer, position, velocity, spaceship)
+    (renderer, position, previous, velocity, spaceship)
 }
 
-pub fn fixplanet(sprite: &str, x: f64, y: f64, mass: f64, radius: f64, mut store: &mut ImageStore) -> (Renderer, Position, Celestial) {
-    let renderer = Renderer::sprite(sprite.to_string(), &mut store);
+pub fn fixplanet(sprite: &str, x: f64, y: f64, mass: f64, radius: f64, store: &mut ImageStore) -> (Renderer, Position, Celestial) {
+    let renderer = Renderer::sprite(sprite.to_string(), store);
     let position = Position {
         x,
         y,
@@ -44,12 +56,17 @@ pub fn fixplanet(sprite: &str, x: f64, y: f64, mass: f64, radius: f64, mut store
     (renderer, position, planet)
 }
 
-pub fn planet(sprite: &str, x: f64, y: f64, vx: f64, vy: f64, mass: f64, radius: f64, mut store: &mut ImageStore) -> (Renderer, Position, Celestial, Velocity) {
-    let renderer = Renderer::sprite(sprite.to_string(), &mut store);
+#[allow(clippy::too_many_arguments)]
+pub fn planet(sprite: &str, x: f64, y: f64, vx: f64, vy: f64, mass: f64, radius: f64, store: &mut ImageStore) -> (Renderer, Position, PreviousPosition, Celestial, Velocity) {
+    let renderer = Renderer::sprite(sprite.to_string(), store);
     let position = Position {
         x,
         y,
     };
+    let previous = PreviousPosition {
+        x,
+        y,
+    };
     let planet = Celestial {
         mass,
         radius,
@@ -58,22 +75,126 @@ pub fn planet(sprite: &str, x: f64, y: f64, vx: f64, vy: f64, mass: f64, radius:
         x: vx,
         y: vy,
     };
-    (renderer, position, planet, velocity)
+    (renderer, position, previous, planet, velocity)
+}
+
+pub fn orbiting_planet(sprite: &str, x: f64, y: f64, mass: f64, radius: f64, store: &mut ImageStore) -> (Renderer, Position, PreviousPosition, Celestial) {
+    let renderer = Renderer::sprite(sprite.to_string(), store);
+    let position = Position {
+        x,
+        y,
+    };
+    let previous = PreviousPosition {
+        x,
+        y,
+    };
+    let planet = Celestial {
+        mass,
+        radius,
+    };
+    (renderer, position, previous, planet)
 }
 
-pub fn end_zone(x: f64, y: f64) -> (Renderer, Position, EndZone) {
-    let renderer = Renderer::rect(100.0, 100.0, "rgba(250, 126, 55, 0.7)".to_string());
+pub fn atmosphere(planet: Entity, x: f64, y: f64, radius: f64, density: f64) -> (Renderer, Position, PreviousPosition, Atmosphere, Attached) {
+    let mut renderer = Renderer::circle(radius, "rgba(120, 180, 255, 0.2)".to_string());
+    renderer.set_z(-1);
     let position = Position {
-        x: x-50.0,
-        y: y-50.0,
+        x,
+        y,
+    };
+    let previous = PreviousPosition {
+        x,
+        y,
+    };
+    let atmosphere = Atmosphere {
+        planet,
+        radius,
+        density,
+    };
+    let attached = Attached {
+        parent: planet,
+        offset: Position {
+            x: 0.0,
+            y: 0.0,
+        },
+    };
+    (renderer, position, previous, atmosphere, attached)
+}
+
+pub fn end_zone(x: f64, y: f64, shape: ZoneShape, color: String, margin: f64, visit: Option<Entity>) -> (Renderer, Position, EndZone) {
+    let (renderer, position) = match shape {
+        ZoneShape::Rect { width, height } => (Renderer::rect(width, height, color), Position { x: x - width / 2.0, y: y - height / 2.0 }),
+        ZoneShape::Circle { radius } => (Renderer::circle(radius, color), Position { x, y }),
+        ZoneShape::Annulus { inner, outer, .. } => (Renderer::ring(inner, outer, color), Position { x, y }),
     };
     let end = EndZone {
-        width: 100.0,
-        height: 100.0,
+        shape,
+        margin,
+        visit,
+        inside: false,
     };
     (renderer, position, end)
 }
 
+pub fn checkpoint(x: f64, y: f64, radius: f64) -> (Renderer, Position) {
+    let mut renderer = Renderer::circle(radius, "rgba(126, 250, 55, 0.4)".to_string());
+    renderer.set_z(-1);
+    let position = Position {
+        x,
+        y,
+    };
+    (renderer, position)
+}
+
+pub fn trajectory(seconds: f64) -> (Renderer, Position, Trajectory) {
+    let mut renderer = Renderer::path(Vec::new(), "rgba(255, 255, 255, 0.6)".to_string());
+    renderer.set_z(5);
+    let position = Position {
+        x: 0.0,
+        y: 0.0,
+    };
+    (renderer, position, Trajectory { seconds })
+}
+
+pub fn stop(x: f64, y: f64, radius: f64, max_speed: f64) -> (Renderer, Position, Stop) {
+    let mut renderer = Renderer::circle(radius, "rgba(250, 220, 55, 0.4)".to_string());
+    renderer.set_z(-1);
+    let position = Position {
+        x,
+        y,
+    };
+    (renderer, position, Stop { radius, max_speed })
+}
+
+pub fn passenger(stop: Entity, destination: Entity) -> (Passenger,) {
+    (Passenger {
+        destination,
+        trip: Trip::Waiting(stop),
+    },)
+}
+
+pub fn passenger_readout() -> (Renderer, Position, PassengerHUD) {
+    let mut renderer = Renderer::text(String::new(), "white".to_string(), "20px Tsoonami".to_string());
+    renderer.set_fixed(true);
+    renderer.set_z(10);
+    let position = Position {
+        x: 1010.0,
+        y: 40.0,
+    };
+    (renderer, position, PassengerHUD)
+}
+
+pub fn dock_readout() -> (Renderer, Position, DockHUD) {
+    let mut renderer = Renderer::text(String::new(), "white".to_string(), "20px Tsoonami".to_string());
+    renderer.set_fixed(true);
+    renderer.set_z(10);
+    let position = Position {
+        x: 520.0,
+        y: 740.0,
+    };
+    (renderer, position, DockHUD)
+}
+
 pub fn text(text: String, color: String, style: String, x: f64, y: f64) -> (Renderer, Position) {
     let mut renderer = Renderer::text(text, color, style);
     renderer.set_fixed(true);
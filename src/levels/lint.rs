@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::dynamics::{Position, orbit_offset};
+use crate::transform::MAX_CHAIN;
+
+use super::{ObjectiveDef, Pack, Planet, ShapeDef};
+
+/// Problem found in a level file. Parse errors carry the line and column where they happened
+pub struct Issue {
+    pub position: Option<(usize, usize)>,
+    pub world: Option<i32>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, col)) = self.position {
+            write!(f, "{}:{}: ", line, col)?;
+        }
+        if let Some(world) = self.world {
+            write!(f, "world {}: ", world)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+fn issue(world: Option<i32>, message: String) -> Issue {
+    Issue {
+        position: None,
+        world,
+        message,
+    }
+}
+
+/// Position of a planet at the start of the level, adding the offsets of every orbit up its parents
+/// the same way `chained_position` does. `None` when the chain is longer than `MAX_CHAIN`, e.g. a cycle
+fn start_position(planet: &Planet, planets: &[Planet]) -> Option<Position> {
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut current = planet;
+    for _ in 0..=MAX_CHAIN {
+        match current.orbit.as_ref().and_then(|orbit| planets.get(orbit.parent).map(|parent| (orbit, parent))) {
+            Some((orbit, parent)) => {
+                let offset = orbit_offset(orbit.semi_major_axis, orbit.eccentricity, orbit.periapsis.to_radians(), orbit.clockwise, orbit.phase.to_radians());
+                x += offset.x;
+                y += offset.y;
+                current = parent;
+            },
+            None => return Some(Position {
+                x: current.x + x,
+                y: current.y + y,
+            }),
+        }
+    }
+    None
+}
+
+/// `spawned` tells, for every planet of the level, whether its ref is known and it ends up in the game
+fn lint_objective(objective: &ObjectiveDef, spawned: &[bool], passengers: usize, world: Option<i32>, issues: &mut Vec<Issue>) {
+    match objective {
+        ObjectiveDef::Deliver if passengers == 0 => {
+            issues.push(issue(world, "objective delivers passengers but there are none".to_string()));
+        },
+        ObjectiveDef::Orbits { planet, .. } if !spawned.get(*planet).copied().unwrap_or(false) => {
+            issues.push(issue(world, format!("objective orbits missing planet {}", planet)));
+        },
+        ObjectiveDef::Checkpoints { points, .. } if points.is_empty() => {
+            issues.push(issue(world, "objective has no checkpoints".to_string()));
+        },
+        ObjectiveDef::All(objectives) | ObjectiveDef::Any(objectives) => {
+            if objectives.is_empty() {
+                issues.push(issue(world, "objective combines no objectives".to_string()));
+            }
+            for objective in objectives {
+                lint_objective(objective, spawned, passengers, world, issues);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Checks a level pack. `sprite_exists` tells whether a sprite path can be served to the game
+pub fn lint<F>(source: &str, sprite_exists: F) -> Vec<Issue>
+where
+    F: Fn(&str) -> bool
+{
+    let root: Pack = match ron::from_str(source) {
+        Ok(root) => root,
+        Err(error) => {
+            let position = if error.position.line == 0 { None } else { Some((error.position.line, error.position.col)) };
+            return vec![Issue {
+                position,
+                world: None,
+                message: error.code.to_string(),
+            }];
+        }
+    };
+    let mut issues = Vec::new();
+
+    let mut planet_ids = HashSet::new();
+    for planet_def in &root.planets {
+        if !planet_ids.insert(planet_def.id.as_str()) {
+            issues.push(issue(None, format!("duplicate planet definition '{}'", planet_def.id)));
+        }
+        if !sprite_exists(&planet_def.sprite) {
+            issues.push(issue(None, format!("planet '{}' uses missing sprite '{}'", planet_def.id, planet_def.sprite)));
+        }
+        if let Some(atmosphere) = &planet_def.atmosphere {
+            if atmosphere.radius <= planet_def.radius {
+                issues.push(issue(None, format!("planet '{}' has an atmosphere inside its surface", planet_def.id)));
+            }
+        }
+    }
+
+    let mut world_ids = HashSet::new();
+    for world in &root.worlds {
+        let id = Some(world.id);
+        if !world_ids.insert(world.id) {
+            issues.push(issue(id, "duplicate world id".to_string()));
+        }
+        let annulus = matches!(world.end.shape, ShapeDef::Annulus { .. });
+        if !annulus && world.end.attach.is_none() && (world.end.x.is_none() || world.end.y.is_none()) {
+            issues.push(issue(id, "end zone needs x and y unless it is attached to a planet".to_string()));
+        }
+        // Centre of the end zone at the start of the level. Annulus zones sit on their planet
+        let (offset_x, offset_y, anchor) = match world.end.shape {
+            ShapeDef::Annulus { planet, .. } => (0.0, 0.0, Some(planet)),
+            _ => {
+                let (x, y) = world.end.offset();
+                (x, y, world.end.attach)
+            },
+        };
+        let (end_x, end_y) = match anchor.and_then(|index| world.planets.get(index)).and_then(|planet| start_position(planet, &world.planets)) {
+            Some(position) => (position.x + offset_x, position.y + offset_y),
+            None => (offset_x, offset_y),
+        };
+        for (index, planet) in world.planets.iter().enumerate() {
+            let planet_def = match root.planets.iter().find(|x| x.id == planet.r#ref) {
+                Some(planet_def) => planet_def,
+                None => {
+                    issues.push(issue(id, format!("planet {} has unknown ref '{}'", index, planet.r#ref)));
+                    continue;
+                }
+            };
+            if let Some(sprite) = &planet.sprite {
+                if !sprite_exists(sprite) {
+                    issues.push(issue(id, format!("planet {} uses missing sprite '{}'", index, sprite)));
+                }
+            }
+            let radius = planet.radius(planet_def);
+            if radius <= 0.0 || planet.scale(planet_def) <= 0.0 {
+                issues.push(issue(id, format!("planet {} has a non positive radius or scale", index)));
+            }
+            if (planet_def.fix || planet.orbit.is_some()) && (planet.vx.is_some() || planet.vy.is_some()) {
+                issues.push(issue(id, format!("planet {} has an initial velocity but does not move freely", index)));
+            }
+            if let Some(orbit) = &planet.orbit {
+                if orbit.parent >= world.planets.len() || orbit.parent == index {
+                    issues.push(issue(id, format!("planet {} orbits invalid parent {}", index, orbit.parent)));
+                }
+                if !(0.0..1.0).contains(&orbit.eccentricity) {
+                    issues.push(issue(id, format!("planet {} has eccentricity {} outside [0, 1)", index, orbit.eccentricity)));
+                }
+            }
+
+            let position = match start_position(planet, &world.planets) {
+                Some(position) => position,
+                None => {
+                    issues.push(issue(id, format!("planet {} orbits in a cycle or more than {} levels deep", index, MAX_CHAIN)));
+                    continue;
+                }
+            };
+            let ship = &world.spaceship;
+            if ((ship.x - position.x).powi(2) + (ship.y - position.y).powi(2)).sqrt() < radius {
+                issues.push(issue(id, format!("spaceship spawns inside planet {} ('{}')", index, planet.r#ref)));
+            }
+            let end = &world.end;
+            let overlaps = match end.shape {
+                ShapeDef::Rect { width, height } => {
+                    // Closest point of the end zone to the planet centre
+                    let x = position.x.max(end_x - width / 2.0).min(end_x + width / 2.0);
+                    let y = position.y.max(end_y - height / 2.0).min(end_y + height / 2.0);
+                    ((x - position.x).powi(2) + (y - position.y).powi(2)).sqrt() < radius
+                },
+                ShapeDef::Circle { radius: zone } => ((end_x - position.x).powi(2) + (end_y - position.y).powi(2)).sqrt() < zone + radius,
+                ShapeDef::Annulus { inner, outer, .. } => {
+                    let distance = ((end_x - position.x).powi(2) + (end_y - position.y).powi(2)).sqrt();
+                    distance + radius > inner && distance - radius < outer
+                },
+            };
+            if overlaps {
+                issues.push(issue(id, format!("end zone overlaps planet {} ('{}')", index, planet.r#ref)));
+            }
+        }
+        if let Some(objective) = &world.objective {
+            let spawned: Vec<bool> = world.planets.iter().map(|planet| root.planets.iter().any(|x| x.id == planet.r#ref)).collect();
+            lint_objective(objective, &spawned, world.passengers.len(), id, &mut issues);
+        }
+        if let ShapeDef::Annulus { planet, inner, outer } = world.end.shape {
+            if planet >= world.planets.len() {
+                issues.push(issue(id, format!("end zone surrounds missing planet {}", planet)));
+            }
+            if inner + world.end.margin >= outer - world.end.margin {
+                issues.push(issue(id, "end zone band is thinner than its margins".to_string()));
+            }
+        }
+        for (index, stop) in world.stops.iter().enumerate() {
+            if stop.attach.is_some_and(|attach| attach >= world.planets.len()) {
+                issues.push(issue(id, format!("stop {} is attached to a missing planet", index)));
+            }
+        }
+        for (index, passenger) in world.passengers.iter().enumerate() {
+            if passenger.from >= world.stops.len() || passenger.to >= world.stops.len() {
+                issues.push(issue(id, format!("passenger {} uses a missing stop", index)));
+            } else if passenger.from == passenger.to {
+                issues.push(issue(id, format!("passenger {} is already at their destination", index)));
+            }
+        }
+        if !world.passengers.is_empty() && world.spaceship.capacity == 0 {
+            issues.push(issue(id, "spaceship has no room for passengers".to_string()));
+        }
+        if let Some(zoom) = &world.zoom {
+            if zoom.min <= 0.0 || zoom.min > zoom.max {
+                issues.push(issue(id, format!("zoom limits {} to {} are invalid", zoom.min, zoom.max)));
+            }
+        }
+        if let Some(minimap) = &world.minimap {
+            if minimap.size <= 0.0 {
+                issues.push(issue(id, format!("minimap size {} is invalid", minimap.size)));
+            }
+            if !minimap.key.is_ascii_alphanumeric() {
+                issues.push(issue(id, format!("minimap key {:?} is not a letter or digit", minimap.key)));
+            }
+            // Thrust, brake and camera keys of the browser frontend
+            if matches!(minimap.key.to_ascii_uppercase(), 'W' | 'S' | 'C') {
+                issues.push(issue(id, format!("minimap key {:?} is already bound", minimap.key)));
+            }
+        }
+        if let Some(attach) = world.end.attach {
+            if attach >= world.planets.len() {
+                issues.push(issue(id, format!("end zone is attached to missing planet {}", attach)));
+            }
+        }
+        if let Some(visit) = world.end.visit {
+            if visit >= world.planets.len() {
+                issues.push(issue(id, format!("end zone requires visiting missing planet {}", visit)));
+            }
+        }
+    }
+    issues
+}
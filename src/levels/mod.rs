@@ -1,16 +1,68 @@
-use serde::{Deserialize};
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
 
-use crate::ImageStore;
+use crate::{ImageStore, ZoneShape};
+use crate::docking::Dock;
+use crate::landing::LandingRules;
+use crate::objectives::{Goal, Objective};
+use crate::renderer::{Camera, CameraMode, Renderer, fit_camera};
+use crate::minimap::{Corner, Minimap};
+use crate::transform::{Attached, system_attach};
+use crate::dynamics::{Gravity, Integrator, Orbit, Physics, Position, PreviousPosition, orbital_period, system_drag, system_orbit};
 
 mod common;
+mod lint;
+
+pub use lint::{Issue, lint};
+
+#[derive(Deserialize)]
+struct AtmosphereDef {
+    /// Outer radius of the atmosphere, measured from the planet centre
+    radius: f64,
+    /// Drag rate per second, the speed relative to the planet decays as `exp(-density * t)`
+    density: f64,
+}
+
+/// Planet sizes divide the sprite scale, so they must be positive
+fn positive_radius<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let radius = f64::deserialize(deserializer)?;
+    if radius > 0.0 {
+        Ok(radius)
+    } else {
+        Err(D::Error::custom(format!("planet radius must be positive, found {}", radius)))
+    }
+}
 
 #[derive(Deserialize)]
 struct PlanetDef {
     id: String,
     sprite: String,
+    #[serde(deserialize_with = "positive_radius")]
     radius: f64,
     mass: f64,
     fix: bool,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    atmosphere: Option<AtmosphereDef>,
+}
+
+#[derive(Deserialize)]
+struct OrbitDef {
+    /// Index of the parent body in the level planet list
+    parent: usize,
+    semi_major_axis: f64,
+    #[serde(default)]
+    eccentricity: f64,
+    /// Starting mean anomaly, in degrees
+    #[serde(default)]
+    phase: f64,
+    /// Angle of the periapsis, in degrees
+    #[serde(default)]
+    periapsis: f64,
+    #[serde(default)]
+    clockwise: bool,
+    /// Seconds per revolution, derived from the parent mass if missing
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    period: Option<f64>,
 }
 
 #[derive(Deserialize)]
@@ -18,6 +70,41 @@ struct Planet {
     r#ref: String, 
     x: f64,
     y: f64,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    orbit: Option<OrbitDef>,
+    /// Overrides of the PlanetDef values for this instance only
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    mass: Option<f64>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    radius: Option<f64>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    sprite: Option<String>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    scale: Option<f64>,
+    /// Initial velocity of a planet that is neither fixed nor orbiting
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    vx: Option<f64>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    vy: Option<f64>,
+}
+
+impl Planet {
+    fn mass(&self, def: &PlanetDef) -> f64 {
+        self.mass.unwrap_or(def.mass)
+    }
+
+    fn radius(&self, def: &PlanetDef) -> f64 {
+        self.radius.unwrap_or(def.radius)
+    }
+
+    fn sprite<'a>(&'a self, def: &'a PlanetDef) -> &'a str {
+        self.sprite.as_deref().unwrap_or(&def.sprite)
+    }
+
+    /// Sprite scale. Follows the radius override unless it is given explicitly
+    fn scale(&self, def: &PlanetDef) -> f64 {
+        self.scale.unwrap_or_else(|| self.radius(def) / def.radius)
+    }
 }
 
 #[derive(Deserialize)]
@@ -35,13 +122,243 @@ struct SpaceShip {
     y: f64,
     vx: f64,
     vy: f64,
-    fuel: f64
+    fuel: f64,
+    /// Passengers that fit aboard at once
+    #[serde(default = "default_capacity")]
+    capacity: u32,
+}
+
+fn default_capacity() -> u32 {
+    4
+}
+
+fn default_stop_radius() -> f64 {
+    60.0
+}
+
+fn default_stop_speed() -> f64 {
+    60.0
+}
+
+#[derive(Deserialize)]
+struct StopDef {
+    /// Centre of the stop, relative to the `attach` planet if there is one
+    x: f64,
+    y: f64,
+    #[serde(default = "default_stop_radius")]
+    radius: f64,
+    /// Maximum speed relative to the stop to pick up or drop off passengers
+    #[serde(default = "default_stop_speed")]
+    speed: f64,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    attach: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct PassengerDef {
+    /// Indexes in the level stop list
+    from: usize,
+    to: usize,
+    #[serde(default = "default_passenger_count")]
+    count: u32,
+}
+
+fn default_passenger_count() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+enum ShapeDef {
+    Rect {
+        width: f64,
+        height: f64,
+    },
+    Circle {
+        radius: f64,
+    },
+    /// Band around the planet with index `planet` in the level planet list
+    Annulus {
+        planet: usize,
+        inner: f64,
+        outer: f64,
+    },
+}
+
+impl Default for ShapeDef {
+    fn default() -> Self {
+        ShapeDef::Rect {
+            width: 100.0,
+            height: 100.0,
+        }
+    }
+}
+
+fn default_zone_color() -> String {
+    "rgba(250, 126, 55, 0.7)".to_string()
+}
+
+fn default_zone_margin() -> f64 {
+    20.0
+}
+
+#[derive(Deserialize)]
+struct DockDef {
+    speed: f64,
+    /// Maximum deviation from `direction`, in degrees. Any approach if missing
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    angle: Option<f64>,
+    /// Direction the spaceship must move towards relative to the zone, in degrees
+    #[serde(default)]
+    direction: f64,
 }
 
 #[derive(Deserialize)]
 struct End {
+    /// Centre of the zone, relative to the `attach` planet if there is one. Unused by annulus zones.
+    /// Only attached zones may leave it out to sit on their planet, the linter flags the others
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    x: Option<f64>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    y: Option<f64>,
+    #[serde(default)]
+    shape: ShapeDef,
+    /// Index of a planet in the level planet list that the zone moves with
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    attach: Option<usize>,
+    #[serde(default = "default_zone_color")]
+    color: String,
+    #[serde(default = "default_zone_margin")]
+    margin: f64,
+    /// Relative speed and angle needed for the zone to count
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    dock: Option<DockDef>,
+    /// Index of a planet in the level planet list that must be landed on before reaching the zone
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    visit: Option<usize>,
+}
+
+impl End {
+    fn offset(&self) -> (f64, f64) {
+        (self.x.unwrap_or(0.0), self.y.unwrap_or(0.0))
+    }
+}
+
+#[derive(Deserialize)]
+struct PointDef {
     x: f64,
-    y: f64
+    y: f64,
+}
+
+fn default_checkpoint_radius() -> f64 {
+    60.0
+}
+
+#[derive(Deserialize)]
+enum ObjectiveDef {
+    Reach {
+        #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+        speed: Option<f64>,
+    },
+    Checkpoints {
+        points: Vec<PointDef>,
+        #[serde(default = "default_checkpoint_radius")]
+        radius: f64,
+    },
+    /// `planet` is an index in the level planet list
+    Orbits {
+        planet: usize,
+        count: f64,
+    },
+    Survive {
+        seconds: f64,
+    },
+    Deliver,
+    All(Vec<ObjectiveDef>),
+    Any(Vec<ObjectiveDef>),
+}
+
+impl ObjectiveDef {
+    /// Indexes in the level planet list of the planets the objective refers to
+    fn planets(&self) -> Vec<usize> {
+        match self {
+            ObjectiveDef::Orbits { planet, .. } => vec![*planet],
+            ObjectiveDef::All(objectives) | ObjectiveDef::Any(objectives) => objectives.iter().flat_map(ObjectiveDef::planets).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Builds the objective tree, spawning checkpoint markers. None if it refers to a missing planet
+    fn spawn(&self, world: &mut hecs::World, planets: &[Option<(hecs::Entity, f64)>]) -> Option<Goal> {
+        let objective = match self {
+            ObjectiveDef::Reach { speed } => Objective::Reach { max_speed: *speed },
+            ObjectiveDef::Checkpoints { points, radius } => Objective::Checkpoints {
+                points: points.iter().map(|point| world.spawn(common::checkpoint(point.x, point.y, *radius))).collect(),
+                radius: *radius,
+                next: 0,
+            },
+            ObjectiveDef::Orbits { planet, count } => Objective::Orbits {
+                planet: planets.get(*planet).cloned().flatten()?.0,
+                count: *count,
+                angle: 0.0,
+                last: None,
+            },
+            ObjectiveDef::Survive { seconds } => Objective::Survive { seconds: *seconds, time: 0.0 },
+            ObjectiveDef::Deliver => Objective::Deliver,
+            ObjectiveDef::All(objectives) => Objective::All(objectives.iter().map(|x| x.spawn(world, planets)).collect::<Option<_>>()?),
+            ObjectiveDef::Any(objectives) => Objective::Any(objectives.iter().map(|x| x.spawn(world, planets)).collect::<Option<_>>()?),
+        };
+        Some(Goal::new(objective))
+    }
+}
+
+#[derive(Deserialize)]
+struct LandingDef {
+    speed: f64,
+    /// Maximum deviation from a vertical approach, in degrees
+    angle: f64,
+    #[serde(default)]
+    refuel: bool,
+}
+
+#[derive(Deserialize)]
+struct PhysicsDef {
+    #[serde(default)]
+    integrator: Integrator,
+    #[serde(default)]
+    gravity: Gravity,
+    #[serde(default)]
+    nbody: bool,
+}
+
+#[derive(Deserialize)]
+struct ZoomDef {
+    min: f64,
+    max: f64,
+}
+
+fn default_minimap_size() -> f64 {
+    200.0
+}
+
+fn default_minimap_key() -> char {
+    'M'
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct MinimapDef {
+    #[serde(default)]
+    corner: Corner,
+    #[serde(default = "default_minimap_size")]
+    size: f64,
+    /// Letter or digit that shows and hides the minimap
+    #[serde(default = "default_minimap_key")]
+    key: char,
+    #[serde(default = "default_true")]
+    visible: bool,
 }
 
 #[derive(Deserialize)]
@@ -53,48 +370,282 @@ struct World {
     texts: Option<Vec<Text>>,
     spaceship: SpaceShip,
     end: End,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    physics: Option<PhysicsDef>,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    landing: Option<LandingDef>,
+    #[serde(default)]
+    stops: Vec<StopDef>,
+    #[serde(default)]
+    passengers: Vec<PassengerDef>,
+    /// What must be done to win. Delivering every passenger if there are any, otherwise reaching the end zone
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    objective: Option<ObjectiveDef>,
+    /// Zoom limits for the wheel and pinch gestures
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    zoom: Option<ZoomDef>,
+    /// Camera mode the level starts with, the player can switch it while playing
+    #[serde(default)]
+    camera: CameraMode,
+    #[serde(default, with = "::serde_with::rust::unwrap_or_skip")]
+    minimap: Option<MinimapDef>,
+    /// Seconds of predicted trajectory shown ahead of the spaceship, 0 disables it
+    #[serde(default = "default_preview")]
+    preview: f64,
+}
+
+fn default_preview() -> f64 {
+    3.0
 }
 
+/// A level file. Planet references are resolved inside the pack that uses them
 #[derive(Deserialize)]
-struct Root {
+struct Pack {
+    name: String,
+    author: String,
+    version: String,
     worlds: Vec<World>,
     planets: Vec<PlanetDef>,
 }
 
-const LEVELS_STRING: &'static str = include_str!("levels.ron");
+impl Pack {
+    /// Rejects objectives about planets that are not in the level, or whose ref is unknown, as they could never be met
+    fn check(&self) -> Result<(), String> {
+        for world in &self.worlds {
+            for index in world.objective.iter().flat_map(ObjectiveDef::planets) {
+                let spawned = world.planets.get(index).is_some_and(|planet| self.planets.iter().any(|x| x.id == planet.r#ref));
+                if !spawned {
+                    return Err(format!("world {}: objective orbits missing planet {}", world.id, index));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const LEVELS_STRING: &str = include_str!("levels.ron");
+
+/// Every level pack known to the game. Packs added at runtime are searched before the built-in one
+pub struct Levels {
+    packs: Vec<Pack>,
+}
+
+impl Levels {
+    pub fn builtin() -> Self {
+        let pack: Pack = ron::from_str(LEVELS_STRING).unwrap_or_else(|error| panic!("levels file is corrupted: {}", error));
+        pack.check().unwrap_or_else(|error| panic!("levels file is corrupted: {}", error));
+        Levels {
+            packs: vec![pack],
+        }
+    }
+
+    /// Parses a pack and gives its levels priority over the ones already loaded, so the last pack added wins
+    pub fn add_pack(&mut self, source: &str) -> Result<(), ron::Error> {
+        let pack: Pack = ron::from_str(source)?;
+        pack.check().map_err(ron::Error::custom)?;
+        self.packs.insert(0, pack);
+        Ok(())
+    }
+
+    /// Name, version and author of every pack, in search order
+    pub fn packs(&self) -> Vec<String> {
+        self.packs.iter().map(|pack| format!("{} {} by {}", pack.name, pack.version, pack.author)).collect()
+    }
+
+    pub fn load_level(&self, level: i32, store: &mut ImageStore) -> hecs::World {
+        let found = self.packs.iter().find_map(|pack| pack.worlds.iter().find(|x| x.id == level).map(|world| (pack, world)));
+        match found {
+            Some((pack, level)) => spawn_level(pack, level, store),
+            None => {
+                let mut world = hecs::World::new();
+                crate::hud::build_hud(&mut world, store);
+                world
+            }
+        }
+    }
+}
+
+/// Loads a level from the built-in pack
+pub fn load_level(level: i32, store: &mut ImageStore) -> hecs::World {
+    Levels::builtin().load_level(level, store)
+}
+
+/// Makes `entity` follow `parent`, keeping their current distance
+fn attach_to(world: &mut hecs::World, entity: hecs::Entity, parent: hecs::Entity) {
+    let position = (*world.get::<Position>(entity).unwrap()).clone();
+    let parent_position = (*world.get::<Position>(parent).unwrap()).clone();
+    world.insert(entity, (
+        PreviousPosition {
+            x: position.x,
+            y: position.y,
+        },
+        Attached {
+            parent,
+            offset: Position {
+                x: position.x - parent_position.x,
+                y: position.y - parent_position.y,
+            },
+        },
+    )).unwrap();
+}
 
-pub fn load_level(level: i32, mut store: &mut ImageStore) -> hecs::World {
+fn spawn_level(pack: &Pack, level: &World, store: &mut ImageStore) -> hecs::World {
     let mut world = hecs::World::new();
-    let levels = load_levels();
-    if let Some(level) = levels.worlds.iter().find(|x| x.id == level) {
-        if level.background {
-            world.spawn(common::background(&mut store));
+    let mut physics = Physics::default();
+    if let Some(physics_def) = &level.physics {
+        physics.integrator = physics_def.integrator;
+        physics.gravity = physics_def.gravity;
+        physics.nbody = physics_def.nbody;
+    }
+    world.spawn((physics.clone(),));
+
+    if level.background {
+        world.spawn(common::background(store));
+    }
+
+    let mut planets = Vec::new();
+    for planet in &level.planets {
+        if let Some(planet_def) = pack.planets.iter().find(|x| x.id == planet.r#ref) {
+            let sprite = planet.sprite(planet_def);
+            let mass = planet.mass(planet_def);
+            let radius = planet.radius(planet_def);
+            let entity = if planet.orbit.is_some() {
+                world.spawn(common::orbiting_planet(sprite, planet.x, planet.y, mass, radius, store))
+            } else if planet_def.fix {
+                world.spawn(common::fixplanet(sprite, planet.x, planet.y, mass, radius, store))
+            } else {
+                let vx = planet.vx.unwrap_or(0.0);
+                let vy = planet.vy.unwrap_or(400.0);
+                world.spawn(common::planet(sprite, planet.x, planet.y, vx, vy, mass, radius, store))
+            };
+            world.get_mut::<Renderer>(entity).unwrap().set_scale(planet.scale(planet_def));
+            if let Some(atmosphere) = &planet_def.atmosphere {
+                let atmosphere_radius = atmosphere.radius * radius / planet_def.radius;
+                world.spawn(common::atmosphere(entity, planet.x, planet.y, atmosphere_radius, atmosphere.density));
+            }
+            planets.push(Some((entity, mass)));
+        } else {
+            planets.push(None);
         }
+    }
 
-        for planet in &level.planets {
-            if let Some(planet_def) = levels.planets.iter().find(|x| x.id == planet.r#ref) {
-                if planet_def.fix {
-                    world.spawn(common::fixplanet(&planet_def.sprite, planet.x, planet.y, planet_def.mass, planet_def.radius, &mut store));
-                } else {
-                    world.spawn(common::planet(&planet_def.sprite, planet.x, planet.y, 0.0, 400.0, planet_def.mass, planet_def.radius, &mut store));
-                }
+    for (planet, spawned) in level.planets.iter().zip(&planets) {
+        if let (Some(orbit), Some((entity, _mass))) = (&planet.orbit, spawned) {
+            if let Some(Some((parent, parent_mass))) = planets.get(orbit.parent) {
+                let orbit = Orbit {
+                    parent: *parent,
+                    semi_major_axis: orbit.semi_major_axis,
+                    eccentricity: orbit.eccentricity,
+                    phase: orbit.phase.to_radians(),
+                    periapsis: orbit.periapsis.to_radians(),
+                    clockwise: orbit.clockwise,
+                    period: orbit.period.unwrap_or_else(|| orbital_period(physics.gravity, *parent_mass, orbit.semi_major_axis)),
+                    time: 0.0,
+                };
+                world.insert_one(*entity, orbit).unwrap();
             }
         }
+    }
+    system_orbit(&mut world, 0.0);
+    system_attach(&mut world);
+    system_drag(&mut world, 0.0);
+
+    if let Some(texts) = &level.texts {
+        for text in texts {
+            world.spawn(common::text(text.text.clone(), text.color.clone(), text.style.clone(), text.x, text.y));
+        }
+    }
 
-        if let Some(texts) = &level.texts {
-            for text in texts {
-                world.spawn(common::text(text.text.clone(), text.color.clone(), text.style.clone(), text.x, text.y));
+    world.spawn(common::spaceship(level.spaceship.x, level.spaceship.y, level.spaceship.vx, level.spaceship.vy, level.spaceship.fuel, level.spaceship.capacity, store));
+    let visit = level.end.visit.and_then(|index| planets.get(index).cloned().flatten()).map(|(entity, _mass)| entity);
+    let (end_x, end_y) = level.end.offset();
+    let (shape, attach, offset_x, offset_y) = match level.end.shape {
+        ShapeDef::Rect { width, height } => (ZoneShape::Rect { width, height }, level.end.attach, end_x, end_y),
+        ShapeDef::Circle { radius } => (ZoneShape::Circle { radius }, level.end.attach, end_x, end_y),
+        ShapeDef::Annulus { planet, inner, outer } => (ZoneShape::Annulus { inner, outer }, Some(planet), 0.0, 0.0),
+    };
+    let parent = attach.and_then(|index| planets.get(index).cloned().flatten()).map(|(entity, _mass)| entity);
+    let (x, y) = match parent {
+        Some(parent) => {
+            let position = world.get::<Position>(parent).unwrap();
+            (position.x + offset_x, position.y + offset_y)
+        },
+        None => (end_x, end_y),
+    };
+    let zone = world.spawn(common::end_zone(x, y, shape, level.end.color.clone(), level.end.margin, visit));
+    if let Some(dock) = &level.end.dock {
+        world.insert_one(zone, Dock::new(dock.speed, dock.angle.map(f64::to_radians), dock.direction.to_radians())).unwrap();
+        world.spawn(common::dock_readout());
+    }
+    if let Some(parent) = parent {
+        attach_to(&mut world, zone, parent);
+    }
+
+    let mut stops = Vec::new();
+    for stop in &level.stops {
+        let parent = stop.attach.and_then(|index| planets.get(index).cloned().flatten()).map(|(entity, _mass)| entity);
+        let (x, y) = match parent {
+            Some(parent) => {
+                let position = world.get::<Position>(parent).unwrap();
+                (position.x + stop.x, position.y + stop.y)
+            },
+            None => (stop.x, stop.y),
+        };
+        let entity = world.spawn(common::stop(x, y, stop.radius, stop.speed));
+        if let Some(parent) = parent {
+            attach_to(&mut world, entity, parent);
+        }
+        stops.push(entity);
+    }
+    for passenger in &level.passengers {
+        if let (Some(from), Some(to)) = (stops.get(passenger.from), stops.get(passenger.to)) {
+            for _ in 0..passenger.count {
+                world.spawn(common::passenger(*from, *to));
             }
         }
+    }
+    if !level.passengers.is_empty() {
+        world.spawn(common::passenger_readout());
+    }
 
-        world.spawn(common::spaceship(level.spaceship.x, level.spaceship.y, level.spaceship.vx, level.spaceship.vy, level.spaceship.fuel, &mut store));
-        world.spawn(common::end_zone(level.end.x, level.end.y));
+    let goal = match &level.objective {
+        Some(objective) => objective.spawn(&mut world, &planets).expect("objective planets are checked when the pack is added"),
+        None if !level.passengers.is_empty() => Goal::new(Objective::Deliver),
+        None => Goal::default(),
+    };
+    world.spawn((goal,));
+    if let Some(landing) = &level.landing {
+        world.spawn((LandingRules {
+            max_speed: landing.speed,
+            max_angle: landing.angle.to_radians(),
+            refuel: landing.refuel,
+        },));
+    }
+    if level.preview > 0.0 {
+        world.spawn(common::trajectory(level.preview));
     }
 
-    crate::hud::build_hud(&mut world, &mut store);
+    crate::hud::build_hud(&mut world, store);
+    if let Some(zoom) = &level.zoom {
+        for (_id, camera) in &mut world.query::<&mut Camera>() {
+            camera.min_zoom = zoom.min;
+            camera.max_zoom = zoom.max;
+        }
+    }
+    fit_camera(&mut world);
+    for (_id, camera) in &mut world.query::<&mut Camera>() {
+        camera.mode = level.camera;
+    }
+    if let Some(def) = &level.minimap {
+        for (_id, minimap) in &mut world.query::<&mut Minimap>() {
+            // Key codes of letters and digits are their uppercase ASCII codes
+            *minimap = Minimap {
+                corner: def.corner,
+                size: def.size,
+                key: def.key.to_ascii_uppercase() as u32,
+                visible: def.visible,
+            };
+        }
+    }
     world
 }
-
-fn load_levels() -> Root {
-    ron::from_str(LEVELS_STRING).expect("levels file is corrupted")
-}
\ No newline at end of file
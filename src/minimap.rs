@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use hecs::{Entity, With, World};
+use serde::Deserialize;
+
+use crate::{SpaceShip, EndZone, ZoneShape};
+use crate::dynamics::*;
+use crate::input::Input;
+use crate::renderer::{Color, Renderer, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::trajectory::Trajectory;
+
+/// Distance from the minimap to the screen border. Leaves room for the HUD at the top
+const MINIMAP_MARGIN: f64 = 60.0;
+/// Distance from the minimap to the HUD widgets next to it
+const HUD_GAP: f64 = 20.0;
+/// Right edge of the back/restart buttons, camera readout and thrust/brake controls down the left side
+const LEFT_HUD: f64 = 260.0;
+/// Top edge of the fuel gauge in the bottom right corner
+const FUEL_HUD: f64 = 630.0;
+/// Smallest radius of a planet on the minimap, so small moons are still visible
+const MIN_DOT: f64 = 2.0;
+const KEY_M: u32 = 77;
+/// Colour of end zones drawn without a renderer of their own
+const ZONE_COLOR: &str = "rgba(250, 126, 55, 0.7)";
+
+/// Screen corner where the minimap is drawn
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Overview of the whole level. `key` is the key code that shows and hides it
+pub struct Minimap {
+    pub corner: Corner,
+    pub size: f64,
+    pub key: u32,
+    pub visible: bool,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Minimap {
+            corner: Corner::default(),
+            size: 200.0,
+            key: KEY_M,
+            visible: true,
+        }
+    }
+}
+
+impl Minimap {
+    /// Screen position of the top left corner. Left corners sit right of the buttons and controls,
+    /// and the bottom right one above the fuel gauge, so the minimap never covers the HUD
+    pub fn origin(&self) -> Position {
+        let left = LEFT_HUD + HUD_GAP;
+        let right = SCREEN_WIDTH - MINIMAP_MARGIN - self.size;
+        let bottom = SCREEN_HEIGHT - MINIMAP_MARGIN - self.size;
+        let (x, y) = match self.corner {
+            Corner::TopLeft => (left, MINIMAP_MARGIN),
+            Corner::TopRight => (right, MINIMAP_MARGIN),
+            Corner::BottomLeft => (left, bottom),
+            Corner::BottomRight => (right, FUEL_HUD - HUD_GAP - self.size),
+        };
+        Position { x, y }
+    }
+}
+
+/// What a minimap renderer stands for, so the same entity is updated every frame
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MinimapMark {
+    Background,
+    Zone(Entity),
+    Planet(Entity),
+    Path,
+    Ship,
+}
+
+/// World area covered by the minimap
+struct Bounds {
+    min: Position,
+    max: Position,
+}
+
+impl Bounds {
+    fn include(&mut self, position: &Position, radius: f64) {
+        self.min.x = self.min.x.min(position.x - radius);
+        self.min.y = self.min.y.min(position.y - radius);
+        self.max.x = self.max.x.max(position.x + radius);
+        self.max.y = self.max.y.max(position.y + radius);
+    }
+}
+
+fn mark(mut renderer: Renderer, z: i32) -> Renderer {
+    renderer.set_fixed(true);
+    renderer.set_z(z);
+    renderer
+}
+
+/// Updates the existing marks in place, spawning the new ones and despawning the ones no longer drawn
+fn update_marks(world: &mut World, marks: Vec<(MinimapMark, Renderer, Position)>) {
+    let mut existing: HashMap<MinimapMark, Entity> = world.query::<&MinimapMark>().iter().map(|(id, mark)| (*mark, id)).collect();
+    for (mark, renderer, position) in marks {
+        match existing.remove(&mark) {
+            Some(id) => {
+                if let Ok(mut current) = world.get_mut::<Renderer>(id) {
+                    *current = renderer;
+                }
+                if let Ok(mut current) = world.get_mut::<Position>(id) {
+                    *current = position;
+                }
+            },
+            None => {
+                world.spawn((renderer, position, mark));
+            },
+        }
+    }
+    for id in existing.into_values() {
+        world.despawn(id).ok();
+    }
+}
+
+/// Redraws the minimap from the planets, end zones, spaceship and its predicted path, toggling it on its key
+pub fn system_minimap(world: &mut World, input: &Input) {
+    let marks = minimap_marks(world, input);
+    update_marks(world, marks);
+}
+
+/// Renderers of the minimap for this frame, nothing when it is hidden
+fn minimap_marks(world: &mut World, input: &Input) -> Vec<(MinimapMark, Renderer, Position)> {
+    let (origin, size) = {
+        let mut query = world.query::<&mut Minimap>();
+        let minimap = match query.iter().next() {
+            Some((_id, minimap)) => minimap,
+            None => return Vec::new(),
+        };
+        if input.keys.contains(&minimap.key) {
+            minimap.visible = !minimap.visible;
+        }
+        if !minimap.visible {
+            return Vec::new();
+        }
+        (minimap.origin(), minimap.size)
+    };
+
+    let planets: Vec<(Entity, Position, f64, bool)> = world.query::<(&Position, &Celestial, Option<&Orbit>, Option<&Velocity>)>()
+        .iter()
+        .map(|(id, (position, celestial, orbit, velocity))| (id, position.clone(), celestial.radius, orbit.is_some() || velocity.is_some()))
+        .collect();
+    let zones: Vec<(Entity, Position, ZoneShape, Color)> = world.query::<(&Position, &EndZone, Option<&Renderer>)>()
+        .iter()
+        .map(|(id, (position, zone, renderer))| {
+            let color = renderer.and_then(Renderer::get_color).cloned().unwrap_or_else(|| ZONE_COLOR.to_string());
+            (id, position.clone(), zone.shape.clone(), color)
+        })
+        .collect();
+    let ship = world.query::<With<SpaceShip, &Position>>().iter().map(|(_id, position)| position.clone()).next();
+    let path: Vec<Position> = world.query::<With<Trajectory, &Renderer>>()
+        .iter()
+        .flat_map(|(_id, renderer)| match renderer {
+            Renderer::PathRenderer { points, .. } => points.clone(),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let mut bounds = Bounds {
+        min: Position { x: f64::INFINITY, y: f64::INFINITY },
+        max: Position { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY },
+    };
+    for (_id, position, radius, _moving) in &planets {
+        bounds.include(position, *radius);
+    }
+    for (_id, position, shape, _color) in &zones {
+        match shape {
+            ZoneShape::Rect { width, height } => {
+                bounds.include(position, 0.0);
+                bounds.include(&Position { x: position.x + width, y: position.y + height }, 0.0);
+            },
+            ZoneShape::Circle { radius } => bounds.include(position, *radius),
+            ZoneShape::Annulus { outer, .. } => bounds.include(position, *outer),
+        }
+    }
+    for position in ship.iter().chain(path.iter()) {
+        bounds.include(position, 0.0);
+    }
+    if bounds.min.x > bounds.max.x {
+        return Vec::new();
+    }
+
+    let scale = size / f64::max(bounds.max.x - bounds.min.x, bounds.max.y - bounds.min.y).max(1.0);
+    // Centres the level inside the square
+    let to_map = |position: &Position| Position {
+        x: origin.x + size / 2.0 + (position.x - (bounds.min.x + bounds.max.x) / 2.0) * scale,
+        y: origin.y + size / 2.0 + (position.y - (bounds.min.y + bounds.max.y) / 2.0) * scale,
+    };
+
+    let mut marks = vec![(MinimapMark::Background, mark(Renderer::rect(size, size, "rgba(0, 0, 0, 0.6)".to_string()), 11), origin.clone())];
+    for (id, position, shape, color) in zones {
+        let renderer = match shape {
+            ZoneShape::Rect { width, height } => Renderer::rect(width * scale, height * scale, color),
+            ZoneShape::Circle { radius } => Renderer::circle((radius * scale).max(MIN_DOT), color),
+            ZoneShape::Annulus { inner, outer } => Renderer::ring(inner * scale, (outer * scale).max(MIN_DOT), color),
+        };
+        marks.push((MinimapMark::Zone(id), mark(renderer, 12), to_map(&position)));
+    }
+    for (id, position, radius, moving) in planets {
+        let color = if moving { "#37a0fa" } else { "#aaaaaa" };
+        marks.push((MinimapMark::Planet(id), mark(Renderer::circle((radius * scale).max(MIN_DOT), color.to_string()), 13), to_map(&position)));
+    }
+    if !path.is_empty() {
+        let points = path.iter().map(|point| {
+            let point = to_map(point);
+            Position { x: point.x - origin.x, y: point.y - origin.y }
+        }).collect();
+        marks.push((MinimapMark::Path, mark(Renderer::path(points, "rgba(255, 255, 255, 0.6)".to_string()), 14), origin.clone()));
+    }
+    if let Some(ship) = ship {
+        marks.push((MinimapMark::Ship, mark(Renderer::circle(3.0, "#7efa37".to_string()), 15), to_map(&ship)));
+    }
+    marks
+}
@@ -0,0 +1,151 @@
+use std::f64::consts::PI;
+
+use hecs::{Entity, World};
+
+use crate::{SpaceShip, EndZone, Finish};
+use crate::collision::segment_circle;
+use crate::dynamics::*;
+use crate::passengers::count;
+
+/// Something the spaceship has to do to win a level
+pub enum Objective {
+    /// Enter the end zone, optionally slower than `max_speed`
+    Reach { max_speed: Option<f64> },
+    /// Fly through every marker in order. Markers are despawned once passed
+    Checkpoints { points: Vec<Entity>, radius: f64, next: usize },
+    /// Go around `planet` `count` times, in either direction
+    Orbits { planet: Entity, count: f64, angle: f64, last: Option<f64> },
+    Survive { seconds: f64, time: f64 },
+    /// Take every passenger to their destination stop
+    Deliver,
+    All(Vec<Goal>),
+    Any(Vec<Goal>),
+}
+
+/// An objective and whether it has been met. Objectives stay complete once met
+pub struct Goal {
+    pub objective: Objective,
+    pub done: bool,
+}
+
+impl Goal {
+    pub fn new(objective: Objective) -> Self {
+        Goal {
+            objective,
+            done: false,
+        }
+    }
+}
+
+impl Default for Goal {
+    fn default() -> Self {
+        Goal::new(Objective::Reach { max_speed: None })
+    }
+}
+
+/// State of the spaceship during the last physics step
+struct Ship {
+    previous: Position,
+    position: Position,
+    speed: f64,
+    inside: bool,
+}
+
+fn update(goal: &mut Goal, ship: &Ship, world: &World, passed: &mut Vec<Entity>, delta: f64) -> bool {
+    if goal.done {
+        return true;
+    }
+    goal.done = match &mut goal.objective {
+        Objective::Reach { max_speed } => ship.inside && max_speed.is_none_or(|max_speed| ship.speed <= max_speed),
+        Objective::Checkpoints { points, radius, next } => {
+            while let Some(point) = points.get(*next) {
+                let center = match world.get::<Position>(*point) {
+                    Ok(position) => (*position).clone(),
+                    Err(_) => break,
+                };
+                if segment_circle(&ship.previous, &ship.position, &center, *radius).is_none() {
+                    break;
+                }
+                passed.push(*point);
+                *next += 1;
+            }
+            *next >= points.len()
+        },
+        Objective::Orbits { planet, count, angle, last } => {
+            if let Ok(center) = world.get::<Position>(*planet) {
+                let current = (ship.position.y - center.y).atan2(ship.position.x - center.x);
+                if let Some(last) = last {
+                    let mut step = current - *last;
+                    if step > PI {
+                        step -= 2.0 * PI;
+                    } else if step < -PI {
+                        step += 2.0 * PI;
+                    }
+                    *angle += step;
+                }
+                *last = Some(current);
+            }
+            angle.abs() >= *count * 2.0 * PI
+        },
+        Objective::Deliver => {
+            // A level without passengers cannot be won by delivering them
+            let (_aboard, delivered, total) = count(world);
+            total > 0 && delivered == total
+        },
+        Objective::Survive { seconds, time } => {
+            *time += delta;
+            *time >= *seconds
+        },
+        // Every child is updated each step, so timers and orbit counters keep running
+        Objective::All(goals) => {
+            let mut done = true;
+            for goal in goals {
+                done &= update(goal, ship, world, passed, delta);
+            }
+            done
+        },
+        Objective::Any(goals) => {
+            let mut done = false;
+            for goal in goals {
+                done |= update(goal, ship, world, passed, delta);
+            }
+            done
+        },
+    };
+    goal.done
+}
+
+/// Evaluates the level goal after every physics step and marks the level as reached once it is met
+pub fn system_objectives(world: &mut World, delta: f64) {
+    if world.query::<&Finish>().iter().any(|(_id, finish)| finish.finish || finish.reached || finish.crash) {
+        return;
+    }
+    let inside = world.query::<&EndZone>().iter().any(|(_id, zone)| zone.inside);
+    let ship = world.query::<(&SpaceShip, &Position, Option<&PreviousPosition>, Option<&Velocity>)>()
+        .iter()
+        .map(|(_id, (_spaceship, position, previous, velocity))| Ship {
+            previous: previous.map(|p| Position { x: p.x, y: p.y }).unwrap_or_else(|| position.clone()),
+            position: position.clone(),
+            speed: velocity.map(|v| v.x.hypot(v.y)).unwrap_or(0.0),
+            inside,
+        })
+        .next();
+    let ship = match ship {
+        Some(ship) => ship,
+        None => return,
+    };
+
+    let mut passed = Vec::new();
+    let mut reached = false;
+    for (_id, goal) in &mut world.query::<&mut Goal>() {
+        reached |= update(goal, &ship, world, &mut passed, delta);
+    }
+    for point in passed {
+        world.despawn(point).ok();
+    }
+    if reached {
+        for (_id, finish) in &mut world.query::<&mut Finish>() {
+            finish.reached = true;
+        }
+    }
+}
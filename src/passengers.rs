@@ -0,0 +1,83 @@
+use hecs::{Entity, World};
+
+use crate::{SpaceShip, Finish};
+use crate::dynamics::*;
+
+/// Place where passengers wait for the bus. The spaceship stops at it by slowing down inside `radius`
+pub struct Stop {
+    pub radius: f64,
+    /// Maximum speed relative to the stop to pick up or drop off passengers
+    pub max_speed: f64,
+}
+
+pub enum Trip {
+    /// Waiting at a stop
+    Waiting(Entity),
+    Aboard,
+    Delivered,
+}
+
+/// Someone who wants to go to the `destination` stop
+pub struct Passenger {
+    pub destination: Entity,
+    pub trip: Trip,
+}
+
+/// Stop where the spaceship is currently stopped, if any
+fn stopped_at(world: &World, delta: f64) -> Option<Entity> {
+    let (ship_position, ship_velocity) = world.query::<(&SpaceShip, &Position, Option<&Velocity>)>()
+        .iter()
+        .map(|(_id, (_spaceship, position, velocity))| (position.clone(), velocity.map(|v| (v.x, v.y)).unwrap_or((0.0, 0.0))))
+        .next()?;
+    let mut query = world.query::<(&Stop, &Position, Option<&PreviousPosition>)>();
+    query.iter().find(|(_id, (stop, position, previous))| {
+        let (vx, vy) = match previous {
+            Some(previous) if delta > 0.0 => ((position.x - previous.x) / delta, (position.y - previous.y) / delta),
+            _ => (0.0, 0.0),
+        };
+        (ship_position.x - position.x).hypot(ship_position.y - position.y) < stop.radius
+            && (ship_velocity.0 - vx).hypot(ship_velocity.1 - vy) <= stop.max_speed
+    }).map(|(id, _stop)| id)
+}
+
+/// Drops off the passengers going to the stop the spaceship is stopped at, then picks up the ones waiting there
+pub fn system_passengers(world: &mut World, delta: f64) {
+    if world.query::<&Finish>().iter().any(|(_id, finish)| finish.finish || finish.crash) {
+        return;
+    }
+    let stop = match stopped_at(world, delta) {
+        Some(stop) => stop,
+        None => return,
+    };
+    let capacity = world.query::<&SpaceShip>().iter().map(|(_id, spaceship)| spaceship.capacity).next().unwrap_or(0);
+    let mut aboard = 0;
+    for (_id, passenger) in &mut world.query::<&mut Passenger>() {
+        if let Trip::Aboard = passenger.trip {
+            if passenger.destination == stop {
+                passenger.trip = Trip::Delivered;
+            } else {
+                aboard += 1;
+            }
+        }
+    }
+    for (_id, passenger) in &mut world.query::<&mut Passenger>() {
+        if aboard >= capacity {
+            break;
+        }
+        if let Trip::Waiting(waiting) = passenger.trip {
+            if waiting == stop {
+                passenger.trip = Trip::Aboard;
+                aboard += 1;
+            }
+        }
+    }
+}
+
+/// Passengers aboard, delivered and in total
+pub fn count(world: &World) -> (u32, u32, u32) {
+    world.query::<&Passenger>().iter().fold((0, 0, 0), |(aboard, delivered, total), (_id, passenger)| match passenger.trip {
+        Trip::Waiting(_) => (aboard, delivered, total + 1),
+        Trip::Aboard => (aboard + 1, delivered, total + 1),
+        Trip::Delivered => (aboard, delivered + 1, total + 1),
+    })
+}
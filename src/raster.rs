@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use crate::backend::{Backend, Transform};
+use crate::dynamics::Position;
+
+/// Drawn when a colour cannot be parsed, so mistakes stand out in golden images
+pub const MISSING_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// RGBA image, 4 bytes per pixel row by row
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Transparent black
+    pub fn new(width: usize, height: usize) -> Self {
+        Bitmap {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn filled(width: usize, height: usize, color: [u8; 4]) -> Self {
+        Bitmap {
+            width,
+            height,
+            pixels: color.iter().copied().cycle().take(width * height * 4).collect(),
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Draws `color` over the pixel with source-over alpha blending. Colours are not premultiplied,
+    /// so the destination weighs by its own alpha: `out = (src as + dst ad (1 - as)) / ao`
+    fn blend(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        let i = (y * self.width + x) * 4;
+        let source_alpha = color[3] as u32;
+        let below = self.pixels[i + 3] as u32;
+        // Both terms are scaled by 255 * 255
+        let source_weight = source_alpha * 255;
+        let below_weight = below * (255 - source_alpha);
+        let alpha = source_weight + below_weight;
+        if alpha == 0 {
+            self.pixels[i..i + 4].copy_from_slice(&[0, 0, 0, 0]);
+            return;
+        }
+        for (pixel, source) in self.pixels[i..i + 3].iter_mut().zip(color.iter()) {
+            *pixel = ((*source as u32 * source_weight + *pixel as u32 * below_weight + alpha / 2) / alpha) as u8;
+        }
+        self.pixels[i + 3] = ((alpha + 127) / 255) as u8;
+    }
+
+    /// Netpbm PAM encoding, which keeps the alpha channel without needing a compressor
+    pub fn encode_pam(&self) -> Vec<u8> {
+        let mut data = format!("P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n", self.width, self.height).into_bytes();
+        data.extend_from_slice(&self.pixels);
+        data
+    }
+
+    /// Reads images written by `encode_pam`
+    pub fn decode_pam(data: &[u8]) -> Option<Self> {
+        const END: &[u8] = b"ENDHDR\n";
+        let header_end = data.windows(END.len()).position(|window| window == END)? + END.len();
+        let header = std::str::from_utf8(&data[..header_end]).ok()?;
+        let mut width = None;
+        let mut height = None;
+        for line in header.lines() {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some("WIDTH"), Some(value)) => width = value.parse().ok(),
+                (Some("HEIGHT"), Some(value)) => height = value.parse().ok(),
+                (Some("DEPTH"), Some(value)) if value != "4" => return None,
+                _ => (),
+            }
+        }
+        let (width, height): (usize, usize) = (width?, height?);
+        let pixels = data[header_end..].to_vec();
+        if pixels.len() != width * height * 4 {
+            return None;
+        }
+        Some(Bitmap { width, height, pixels })
+    }
+}
+
+/// Parses the CSS colours used by the levels: `#rgb`, `#rrggbb`, `rgb(...)`, `rgba(...)` and a few names
+pub fn parse_color(color: &str) -> Option<[u8; 4]> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        let digit = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+        return match hex.len() {
+            3 => Some([digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17, 255]),
+            6 => Some([digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, 255]),
+            _ => None,
+        };
+    }
+    if let Some(args) = color.strip_prefix("rgba(").or_else(|| color.strip_prefix("rgb(")) {
+        let values: Vec<f64> = args.strip_suffix(')')?.split(',').map(|value| value.trim().parse().ok()).collect::<Option<_>>()?;
+        let channel = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+        return match values.as_slice() {
+            [r, g, b] => Some([channel(*r), channel(*g), channel(*b), 255]),
+            [r, g, b, a] => Some([channel(*r), channel(*g), channel(*b), channel(a * 255.0)]),
+            _ => None,
+        };
+    }
+    match color {
+        "black" => Some([0, 0, 0, 255]),
+        "white" => Some([255, 255, 255, 255]),
+        "red" => Some([255, 0, 0, 255]),
+        "green" => Some([0, 128, 0, 255]),
+        "blue" => Some([0, 0, 255, 255]),
+        "yellow" => Some([255, 255, 0, 255]),
+        "transparent" => Some([0, 0, 0, 0]),
+        _ => None,
+    }
+}
+
+/// Pure Rust backend that draws into a `Bitmap`, for headless rendering and golden image tests.
+/// There is no anti-aliasing: a pixel is painted when its centre is inside the shape. Text has no
+/// glyphs, every visible character is a block as tall as the font size says
+pub struct Raster {
+    target: Bitmap,
+    transform: Transform,
+    stack: Vec<Transform>,
+    images: HashMap<String, Bitmap>,
+}
+
+impl Raster {
+    pub fn new(width: usize, height: usize) -> Self {
+        Raster {
+            target: Bitmap::new(width, height),
+            transform: Transform::IDENTITY,
+            stack: Vec::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    /// Makes `image` available to sprites. Sprites whose image was not added are not drawn
+    pub fn add_image(&mut self, name: &str, bitmap: Bitmap) {
+        self.images.insert(name.to_string(), bitmap);
+    }
+
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.target
+    }
+
+    /// Paints every pixel whose centre, taken back to local coordinates, gives a colour.
+    /// `min` and `max` bound the shape in local coordinates
+    fn fill<F: Fn(f64, f64) -> Option<[u8; 4]>>(&mut self, min: (f64, f64), max: (f64, f64), shade: F) {
+        let inverse = match self.transform.inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        let corners = [
+            self.transform.apply(min.0, min.1),
+            self.transform.apply(max.0, min.1),
+            self.transform.apply(min.0, max.1),
+            self.transform.apply(max.0, max.1),
+        ];
+        let left = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).floor().max(0.0) as usize;
+        let top = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).floor().max(0.0) as usize;
+        let right = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).ceil().min(self.target.width as f64);
+        let bottom = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).ceil().min(self.target.height as f64);
+        if right <= 0.0 || bottom <= 0.0 {
+            return;
+        }
+        for y in top..bottom as usize {
+            for x in left..right as usize {
+                let (lx, ly) = inverse.apply(x as f64 + 0.5, y as f64 + 0.5);
+                if let Some(color) = shade(lx, ly) {
+                    self.target.blend(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+impl Backend for Raster {
+    fn clear(&mut self) {
+        self.target = Bitmap::new(self.target.width, self.target.height);
+    }
+
+    fn save(&mut self) {
+        self.stack.push(self.transform);
+    }
+
+    fn restore(&mut self) {
+        if let Some(transform) = self.stack.pop() {
+            self.transform = transform;
+        }
+    }
+
+    fn translate(&mut self, x: f64, y: f64) {
+        self.transform = self.transform.then(Transform::translation(x, y));
+    }
+
+    fn scale(&mut self, x: f64, y: f64) {
+        self.transform = self.transform.then(Transform::scaling(x, y));
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.transform = self.transform.then(Transform::rotation(angle));
+    }
+
+    fn circle(&mut self, center: &Position, radius: f64, color: &str) {
+        self.ring(center, 0.0, radius, color);
+    }
+
+    fn ring(&mut self, center: &Position, inner: f64, outer: f64, color: &str) {
+        let color = parse_color(color).unwrap_or(MISSING_COLOR);
+        let (cx, cy) = (center.x, center.y);
+        self.fill((cx - outer, cy - outer), (cx + outer, cy + outer), |x, y| {
+            let distance = (x - cx).hypot(y - cy);
+            if distance <= outer && distance >= inner { Some(color) } else { None }
+        });
+    }
+
+    fn rect(&mut self, position: &Position, width: f64, height: f64, color: &str) {
+        let color = parse_color(color).unwrap_or(MISSING_COLOR);
+        let (left, top) = (position.x, position.y);
+        self.fill((left, top), (left + width, top + height), |x, y| {
+            if x >= left && x < left + width && y >= top && y < top + height { Some(color) } else { None }
+        });
+    }
+
+    fn text(&mut self, text: &str, position: &Position, font: &str, color: &str) {
+        // Only the size in pixels is used, e.g. "20px Tsoonami"
+        let size = font.split_whitespace()
+            .find_map(|part| part.strip_suffix("px")?.parse::<f64>().ok())
+            .unwrap_or(10.0);
+        for (i, character) in text.chars().enumerate() {
+            if character.is_whitespace() {
+                continue;
+            }
+            let corner = Position {
+                x: position.x + i as f64 * size * 0.6,
+                y: position.y - size * 0.7,
+            };
+            self.rect(&corner, size * 0.5, size * 0.7, color);
+        }
+    }
+
+    fn image(&mut self, image: &str, position: &Position, width: f64, height: f64) {
+        let bitmap = match self.images.get(image) {
+            Some(bitmap) if bitmap.width > 0 && bitmap.height > 0 => bitmap.clone(),
+            _ => return,
+        };
+        let (left, top) = (position.x, position.y);
+        // Nearest neighbour sampling
+        self.fill((left, top), (left + width, top + height), |x, y| {
+            let u = (x - left) / width;
+            let v = (y - top) / height;
+            if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                return None;
+            }
+            Some(bitmap.pixel((u * bitmap.width as f64) as usize, (v * bitmap.height as f64) as usize))
+        });
+    }
+
+    fn image_size(&self, image: &str) -> Option<(f64, f64)> {
+        self.images.get(image).map(|bitmap| (bitmap.width as f64, bitmap.height as f64))
+    }
+}
@@ -1,19 +1,147 @@
-use std::f64;
+use std::collections::HashMap;
+
+use serde::Deserialize;
 
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
 use hecs::{World, With};
 use itertools::Itertools;
 
-use crate::{SpaceShip, ImageStore};
-use crate::web::*;
+use crate::{SpaceShip, EndZone, ZoneShape};
 use crate::dynamics::*;
+use crate::input::Input;
+use crate::backend::Backend;
+
+pub type Color = String;
+pub type Font = String;
+
+#[cfg(feature = "web")]
+pub type Image = web_sys::HtmlImageElement;
+
+/// Stand-in for an image when running without a browser. It has no pixels, so its size is zero
+#[cfg(not(feature = "web"))]
+pub struct Image;
+
+#[cfg(not(feature = "web"))]
+impl Image {
+    pub fn width(&self) -> u32 {
+        0
+    }
+    pub fn height(&self) -> u32 {
+        0
+    }
+}
+
+pub type ImageStore = HashMap<String, Image>;
+
+#[cfg(feature = "web")]
+fn load_image(url: &str) -> Image {
+    use wasm_bindgen::JsCast;
+
+    let img = crate::web::document().create_element("img")
+        .expect("Unable to create img element")
+        .dyn_into::<web_sys::HtmlImageElement>()
+        .unwrap();
+    img.set_src(url);
+    img
+}
+
+#[cfg(not(feature = "web"))]
+fn load_image(_url: &str) -> Image {
+    Image
+}
 
-type Color = String;
-type Font = String;
+pub const SCREEN_WIDTH: f64 = 1360.0;
+pub const SCREEN_HEIGHT: f64 = 768.0;
+/// The spaceship is kept at least this far from the screen border
+const SCREEN_MARGIN: f64 = 100.0;
+/// Zoom change of one wheel notch
+pub const ZOOM_STEP: f64 = 1.1;
 
+pub const MIN_ZOOM: f64 = 0.25;
+pub const MAX_ZOOM: f64 = 2.0;
+
+/// Seconds of movement the follow camera looks ahead of the spaceship
+const LOOK_AHEAD: f64 = 0.5;
+/// How fast the follow and frame cameras catch up, per second
+const CAMERA_EASING: f64 = 4.0;
+
+/// How the camera moves with the spaceship
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub enum CameraMode {
+    /// Snaps only when the spaceship gets close to the screen border. Original behaviour
+    #[default]
+    Edge,
+    /// Eases towards the spaceship, looking ahead along its velocity
+    Follow,
+    /// Keeps the spaceship and the end zone in view, zooming as needed
+    Frame,
+    /// Only moves when dragged
+    Free,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Edge => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::Frame,
+            CameraMode::Frame => CameraMode::Free,
+            CameraMode::Free => CameraMode::Edge,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Edge => "Edge",
+            CameraMode::Follow => "Follow",
+            CameraMode::Frame => "Frame",
+            CameraMode::Free => "Free",
+        }
+    }
+}
+
+/// World to screen transform: `screen = world * zoom + offset`
 pub struct Camera {
-    pub offset: Position
+    pub mode: CameraMode,
+    pub offset: Position,
+    pub zoom: f64,
+    pub min_zoom: f64,
+    pub max_zoom: f64,
+}
+
+impl Camera {
+    pub fn new(min_zoom: f64, max_zoom: f64) -> Self {
+        Camera {
+            mode: CameraMode::default(),
+            offset: Position {
+                x: 0.0,
+                y: 0.0,
+            },
+            zoom: 1.0,
+            min_zoom,
+            max_zoom,
+        }
+    }
+
+    pub fn to_screen(&self, position: &Position) -> Position {
+        Position {
+            x: position.x * self.zoom + self.offset.x,
+            y: position.y * self.zoom + self.offset.y,
+        }
+    }
+
+    /// Changes the zoom keeping the world point under `center` (in screen coordinates) in place
+    pub fn zoom_at(&mut self, zoom: f64, center: &Position) {
+        let zoom = zoom.max(self.min_zoom).min(self.max_zoom);
+        let ratio = zoom / self.zoom;
+        self.offset.x = center.x - (center.x - self.offset.x) * ratio;
+        self.offset.y = center.y - (center.y - self.offset.y) * ratio;
+        self.zoom = zoom;
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(MIN_ZOOM, MAX_ZOOM)
+    }
 }
 
 pub enum Renderer {
@@ -37,8 +165,23 @@ pub enum Renderer {
         z: i32,
         fixed: bool,
     },
+    /// Filled band between two circles
+    RingRenderer {
+        inner: f64,
+        outer: f64,
+        color: Color,
+        z: i32,
+        fixed: bool,
+    },
     SpriteRenderer {
         image: String,
+        scale: f64,
+        z: i32,
+        fixed: bool,
+    },
+    PathRenderer {
+        points: Vec<Position>,
+        color: Color,
         z: i32,
         fixed: bool,
     }
@@ -53,6 +196,15 @@ impl Renderer {
             fixed: false
         }
     }
+    pub fn ring(inner: f64, outer: f64, color: Color) -> Self {
+        Renderer::RingRenderer {
+            inner,
+            outer,
+            color,
+            z: 0,
+            fixed: false
+        }
+    }
     pub fn rect(width: f64, height: f64, color: Color) -> Self {
         Renderer::RectRenderer {
             width,
@@ -71,52 +223,64 @@ impl Renderer {
             fixed: false,
         }
     }
+    pub fn path(points: Vec<Position>, color: Color) -> Self {
+        Renderer::PathRenderer {
+            points,
+            color,
+            z: 0,
+            fixed: false,
+        }
+    }
     pub fn sprite(url: String, store: &mut ImageStore) -> Self {
-        let img = document().create_element("img")
-            .expect("Unable to create img element")
-            .dyn_into::<web_sys::HtmlImageElement>()
-            .unwrap();
-        img.set_src(&url);
-        store.insert(url.clone(), img);
+        store.insert(url.clone(), load_image(&url));
         Renderer::SpriteRenderer {
             image: url,
+            scale: 1.0,
             z: 0,
             fixed: false
         }
     }
-    fn get_width(&self, store: &ImageStore) -> Option<u32> {
+    /// Size of a sprite as drawn. The other renderers are drawn from their position instead of centred on it
+    pub fn get_dimensions<B: Backend>(&self, backend: &B) -> Option<(f64, f64)> {
         match self {
-            Renderer::SpriteRenderer{image, ..} => {
-                let img = store.get(image);
-                img.and_then(|img|{
-                    Some(img.width())
-                })
+            Renderer::SpriteRenderer{image, scale, ..} => {
+                backend.image_size(image).map(|(width, height)| (width * scale, height * scale))
             },
             _ => None
         }
     }
-    fn get_height(&self, store: &ImageStore) -> Option<u32> {
+    pub fn paint<B: Backend>(&self, backend: &mut B, position: &Position) {
         match self {
-            Renderer::SpriteRenderer{image, ..} => {
-                let img = store.get(image);
-                img.and_then(|img|{
-                    Some(img.height())
-                })
+            Renderer::CircleRenderer { radius, color, ..} => backend.circle(position, *radius, color),
+            Renderer::RingRenderer { inner, outer, color, ..} => backend.ring(position, *inner, *outer, color),
+            Renderer::RectRenderer { width, height, color, ..} => backend.rect(position, *width, *height, color),
+            Renderer::TextRenderer { color, font, text, .. } => backend.text(text, position, font, color),
+            Renderer::SpriteRenderer { image, .. } => {
+                if let Some((width, height)) = self.get_dimensions(backend) {
+                    backend.image(image, position, width, height);
+                }
             },
-            _ => None
+            Renderer::PathRenderer { points, color, ..} => {
+                for point in points {
+                    backend.circle(&Position { x: position.x + point.x, y: position.y + point.y }, 2.0, color);
+                }
+            }
         }
     }
-    fn get_dimensions(&self, store: &ImageStore) -> Option<(u32, u32)> {
-        let width = self.get_width(store);
-        let height = self.get_height(store);
-        width.zip(height)
+    /// Only sprites can be scaled, the other renderers carry their own size
+    pub fn set_scale(&mut self, new_scale: f64) {
+        if let Renderer::SpriteRenderer{scale, ..} = self {
+            *scale = new_scale;
+        }
     }
     pub fn set_z(&mut self, new_z: i32) {
         match self {
             Renderer::CircleRenderer{z, ..} => *z = new_z,
             Renderer::RectRenderer{z, ..} => *z = new_z,
             Renderer::TextRenderer{z, ..} => *z = new_z,
+            Renderer::RingRenderer{z, ..} => *z = new_z,
             Renderer::SpriteRenderer{z, ..} => *z = new_z,
+            Renderer::PathRenderer{z, ..} => *z = new_z,
         }
     }
     pub fn get_z(&self) -> i32 {
@@ -124,7 +288,20 @@ impl Renderer {
             Renderer::CircleRenderer{z, ..} => *z,
             Renderer::RectRenderer{z, ..} => *z,
             Renderer::TextRenderer{z, ..} => *z,
+            Renderer::RingRenderer{z, ..} => *z,
             Renderer::SpriteRenderer{z, ..} => *z,
+            Renderer::PathRenderer{z, ..} => *z,
+        }
+    }
+    /// Sprites draw their image and have no colour
+    pub fn get_color(&self) -> Option<&Color> {
+        match self {
+            Renderer::CircleRenderer{color, ..} => Some(color),
+            Renderer::RectRenderer{color, ..} => Some(color),
+            Renderer::TextRenderer{color, ..} => Some(color),
+            Renderer::RingRenderer{color, ..} => Some(color),
+            Renderer::SpriteRenderer{..} => None,
+            Renderer::PathRenderer{color, ..} => Some(color),
         }
     }
     pub fn set_fixed(&mut self, new_fixed: bool) {
@@ -132,7 +309,9 @@ impl Renderer {
             Renderer::CircleRenderer{fixed, ..} => *fixed = new_fixed,
             Renderer::RectRenderer{fixed, ..} => *fixed = new_fixed,
             Renderer::TextRenderer{fixed, ..} => *fixed = new_fixed,
+            Renderer::RingRenderer{fixed, ..} => *fixed = new_fixed,
             Renderer::SpriteRenderer{fixed, ..} => *fixed = new_fixed,
+            Renderer::PathRenderer{fixed, ..} => *fixed = new_fixed,
         }
     }
     pub fn is_fixed(&self) -> bool {
@@ -140,90 +319,171 @@ impl Renderer {
             Renderer::CircleRenderer{fixed, ..} => *fixed,
             Renderer::RectRenderer{fixed, ..} => *fixed,
             Renderer::TextRenderer{fixed, ..} => *fixed,
+            Renderer::RingRenderer{fixed, ..} => *fixed,
             Renderer::SpriteRenderer{fixed, ..} => *fixed,
-        }
-    }
-    fn paint(&self, context: &web_sys::CanvasRenderingContext2d, position: &Position, store: &ImageStore) {
-        match self {
-            Renderer::CircleRenderer { radius, color, ..} => {
-                context.begin_path();
-
-                context.set_fill_style(&JsValue::from_str(color));
-        
-                context
-                    .arc(position.x, position.y, *radius, 0.0, f64::consts::PI * 2.0)
-                    .unwrap();
-        
-                context.fill();
-            },
-            Renderer::RectRenderer { width, height, color, ..} => {
-                context.set_fill_style(&JsValue::from_str(color));
-                context.fill_rect(position.x, position.y, *width, *height);
-            },
-            Renderer::TextRenderer {color, font, text, .. } => {
-                context.set_fill_style(&JsValue::from_str(color));
-                context.set_font(font);
-                context.fill_text(text, position.x, position.y).unwrap();
-            },
-            Renderer::SpriteRenderer { image, ..} => {
-                let image = store.get(image);
-                context.draw_image_with_html_image_element(&image.unwrap(), position.x, position.y).unwrap();
-            }
+            Renderer::PathRenderer{fixed, ..} => *fixed,
         }
     }
 }
 
-pub fn system_offset(world: &mut World) {
-    for (_id, position) in &mut world.query::<With<SpaceShip, &Position>>() {
-        for (_id, camera) in &mut world.query::<&mut Camera>() {
-            if position.x + camera.offset.x < 100.0 {
-                camera.offset.x = 100.0 - position.x;
-            }
-            if position.y + camera.offset.y < 100.0 {
-                camera.offset.y = 100.0 - position.y;
-            }
-            if position.x + camera.offset.x > 1260.0 {
-                camera.offset.x = 1260.0 - position.x;
-            }
-            if position.y + camera.offset.y > 668.0 {
-                camera.offset.y = 668.0 - position.y;
-            }
-        }
+/// Position `alpha` of the way from the previous physics position to the current one
+fn interpolate(position: &Position, previous: Option<&PreviousPosition>, alpha: f64) -> Position {
+    match previous {
+        Some(previous) => Position {
+            x: previous.x + (position.x - previous.x) * alpha,
+            y: previous.y + (position.y - previous.y) * alpha,
+        },
+        None => position.clone(),
     }
 }
 
-pub fn system_renderer<'a>(world: &mut World, context: &web_sys::CanvasRenderingContext2d, store: &ImageStore) {
+/// Draws every renderer sorted by z. Moving bodies are interpolated `alpha` of the way between
+/// their previous and current physics positions
+pub fn system_renderer<B: Backend>(world: &mut World, backend: &mut B, alpha: f64) {
     for (_id, camera) in &mut world.query::<&Camera>() {
-        world.query::<(&Renderer, &Position)>()
+        world.query::<(&Renderer, &Position, Option<&PreviousPosition>)>()
             .iter()
-            .sorted_by_key(|(_id, (renderer, _position))| {
+            .sorted_by_key(|(_id, (renderer, _position, _previous))| {
                 renderer.get_z()
-            }).for_each(|(id, (renderer, position))|{
+            }).for_each(|(id, (renderer, position, previous))|{
                 if renderer.is_fixed() {
-                    renderer.paint(&context, &position, &store);
+                    renderer.paint(backend, position);
                 } else {
-                    context.save();
-                    let position = Position {
-                        x: position.x + camera.offset.x,
-                        y: position.y + camera.offset.y,
-                    };
-                    context.translate(position.x, position.y).unwrap();
+                    backend.save();
+                    let position = camera.to_screen(&interpolate(position, previous, alpha));
+                    backend.translate(position.x, position.y);
+                    backend.scale(camera.zoom, camera.zoom);
                     if let Ok(spaceship) = world.get::<SpaceShip>(id){
-                        context.rotate(spaceship.angle-std::f64::consts::FRAC_PI_2).unwrap();
+                        backend.rotate(spaceship.angle-std::f64::consts::FRAC_PI_2);
                     }
-                    if let Some((width, height)) = renderer.get_dimensions(&store) {
-                        let width = width as f64;
-                        let height = height as f64;
+                    if let Some((width, height)) = renderer.get_dimensions(backend) {
                         let position = Position {
                             x: -(width)/2.0,
                             y: -(height)/2.0,
                         };
-                        renderer.paint(&context, &position, &store);
+                        renderer.paint(backend, &position);
                     } else {
-                        renderer.paint(&context, &Position { x: 0.0, y: 0.0}, &store);
+                        renderer.paint(backend, &Position { x: 0.0, y: 0.0});
                     }
-                    context.restore();
+                    backend.restore();
                 }
         });
     }
-}
\ No newline at end of file
+}
+
+/// Moves the camera according to its mode. `delta` is the frame time, used to ease the movement.
+/// It tracks the same interpolated positions `system_renderer` draws with `alpha`
+pub fn system_offset(world: &mut World, input: &mut Input, delta: f64, alpha: f64) {
+    let pan = std::mem::replace(&mut input.pan, (0.0, 0.0));
+    let switch = std::mem::replace(&mut input.camera, false);
+    let target = world.query::<(&EndZone, &Position, Option<&PreviousPosition>)>()
+        .iter()
+        .next()
+        .map(|(_id, (zone, position, previous))| zone.center(&interpolate(position, previous, alpha)));
+    for (_id, (position, previous, velocity)) in &mut world.query::<With<SpaceShip, (&Position, Option<&PreviousPosition>, Option<&Velocity>)>>() {
+        let position = &interpolate(position, previous, alpha);
+        for (_id, camera) in &mut world.query::<&mut Camera>() {
+            if switch {
+                camera.mode = camera.mode.next();
+            }
+            let easing = 1.0 - (-CAMERA_EASING * delta).exp();
+            match camera.mode {
+                CameraMode::Edge => {
+                    let screen = camera.to_screen(position);
+                    if screen.x < SCREEN_MARGIN {
+                        camera.offset.x += SCREEN_MARGIN - screen.x;
+                    }
+                    if screen.y < SCREEN_MARGIN {
+                        camera.offset.y += SCREEN_MARGIN - screen.y;
+                    }
+                    if screen.x > SCREEN_WIDTH - SCREEN_MARGIN {
+                        camera.offset.x += SCREEN_WIDTH - SCREEN_MARGIN - screen.x;
+                    }
+                    if screen.y > SCREEN_HEIGHT - SCREEN_MARGIN {
+                        camera.offset.y += SCREEN_HEIGHT - SCREEN_MARGIN - screen.y;
+                    }
+                },
+                CameraMode::Follow => {
+                    let (vx, vy) = velocity.map(|v| (v.x, v.y)).unwrap_or((0.0, 0.0));
+                    let ahead = Position {
+                        x: position.x + vx * LOOK_AHEAD,
+                        y: position.y + vy * LOOK_AHEAD,
+                    };
+                    camera.offset.x += (SCREEN_WIDTH / 2.0 - ahead.x * camera.zoom - camera.offset.x) * easing;
+                    camera.offset.y += (SCREEN_HEIGHT / 2.0 - ahead.y * camera.zoom - camera.offset.y) * easing;
+                },
+                CameraMode::Frame => {
+                    let target = target.clone().unwrap_or_else(|| position.clone());
+                    let width = (target.x - position.x).abs() + 2.0 * SCREEN_MARGIN;
+                    let height = (target.y - position.y).abs() + 2.0 * SCREEN_MARGIN;
+                    let zoom = f64::min(SCREEN_WIDTH / width, SCREEN_HEIGHT / height).max(camera.min_zoom).min(camera.max_zoom);
+                    camera.zoom += (zoom - camera.zoom) * easing;
+                    let center = Position {
+                        x: (target.x + position.x) / 2.0,
+                        y: (target.y + position.y) / 2.0,
+                    };
+                    camera.offset.x += (SCREEN_WIDTH / 2.0 - center.x * camera.zoom - camera.offset.x) * easing;
+                    camera.offset.y += (SCREEN_HEIGHT / 2.0 - center.y * camera.zoom - camera.offset.y) * easing;
+                },
+                CameraMode::Free => {
+                    camera.offset.x += pan.0;
+                    camera.offset.y += pan.1;
+                },
+            }
+        }
+    }
+}
+
+/// Applies the zoom requested by the wheel or a pinch around the centre of the screen
+pub fn system_zoom(world: &mut World, input: &mut Input) {
+    if input.zoom == 0.0 {
+        return;
+    }
+    let center = Position {
+        x: SCREEN_WIDTH / 2.0,
+        y: SCREEN_HEIGHT / 2.0,
+    };
+    for (_id, camera) in &mut world.query::<&mut Camera>() {
+        let zoom = camera.zoom * ZOOM_STEP.powf(input.zoom);
+        camera.zoom_at(zoom, &center);
+    }
+    input.zoom = 0.0;
+}
+
+/// Zooms out until every celestial body, the spaceship and the end zone are on screen, never zooming in past 1
+pub fn fit_camera(world: &mut World) {
+    let mut min = Position { x: f64::INFINITY, y: f64::INFINITY };
+    let mut max = Position { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY };
+    let mut include = |position: &Position, radius: f64| {
+        min.x = min.x.min(position.x - radius);
+        min.y = min.y.min(position.y - radius);
+        max.x = max.x.max(position.x + radius);
+        max.y = max.y.max(position.y + radius);
+    };
+    for (_id, (position, celestial)) in &mut world.query::<(&Position, &Celestial)>() {
+        include(position, celestial.radius);
+    }
+    for (_id, position) in &mut world.query::<With<SpaceShip, &Position>>() {
+        include(position, SCREEN_MARGIN);
+    }
+    for (_id, (position, zone)) in &mut world.query::<(&Position, &EndZone)>() {
+        match zone.shape {
+            ZoneShape::Rect { width, height } => {
+                include(position, 0.0);
+                include(&Position { x: position.x + width, y: position.y + height }, 0.0);
+            },
+            ZoneShape::Circle { radius } => include(position, radius),
+            ZoneShape::Annulus { outer, .. } => include(position, outer),
+        }
+    }
+    // Levels that fit the screen keep the original framing
+    if min.x > max.x || (min.x >= 0.0 && min.y >= 0.0 && max.x <= SCREEN_WIDTH && max.y <= SCREEN_HEIGHT) {
+        return;
+    }
+    for (_id, camera) in &mut world.query::<&mut Camera>() {
+        let zoom = f64::min(SCREEN_WIDTH / (max.x - min.x), SCREEN_HEIGHT / (max.y - min.y)).min(1.0);
+        camera.zoom = zoom.max(camera.min_zoom).min(camera.max_zoom);
+        camera.offset.x = SCREEN_WIDTH / 2.0 - (min.x + max.x) / 2.0 * camera.zoom;
+        camera.offset.y = SCREEN_HEIGHT / 2.0 - (min.y + max.y) / 2.0 * camera.zoom;
+    }
+}
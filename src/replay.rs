@@ -0,0 +1,147 @@
+use hecs::World;
+
+use crate::Finish;
+use crate::input::Input;
+
+/// Identifies the replay text format, bumped whenever it changes
+const FORMAT: &str = "obr1";
+
+/// Longest recording `decode` accepts, an hour of steps at 1000 per second
+pub const MAX_STEPS: usize = 3_600_000;
+
+/// How a recorded run ended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ending {
+    Crash,
+    Win,
+}
+
+/// Input of every fixed physics step of a run, enough to reproduce it exactly
+#[derive(Clone, PartialEq, Debug)]
+pub struct Recording {
+    pub level: i32,
+    /// Game version the run was recorded with, as physics changes invalidate old recordings
+    pub version: String,
+    pub rate: f64,
+    pub ending: Option<Ending>,
+    pub steps: Vec<(bool, bool)>,
+}
+
+impl Recording {
+    pub fn new(level: i32, rate: f64) -> Self {
+        Recording {
+            level,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            rate,
+            ending: None,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, input: &Input) {
+        self.steps.push((input.forward, input.brake));
+    }
+
+    /// Input to feed at the given step. Past the end of the recording nothing is pressed
+    pub fn input(&self, step: usize) -> Input {
+        let (forward, brake) = self.steps.get(step).copied().unwrap_or((false, false));
+        Input {
+            forward,
+            brake,
+            ..Input::default()
+        }
+    }
+
+    /// Compact text form: header fields followed by run-length encoded steps, e.g. `obr1 0.1.0 3 120 w 40n12f3b`
+    pub fn encode(&self) -> String {
+        let ending = match self.ending {
+            Some(Ending::Crash) => "c",
+            Some(Ending::Win) => "w",
+            None => "-",
+        };
+        let mut runs = String::new();
+        let mut steps = self.steps.iter().peekable();
+        while let Some(step) = steps.next() {
+            let mut count = 1;
+            while steps.peek() == Some(&step) {
+                steps.next();
+                count += 1;
+            }
+            let code = match step {
+                (false, false) => 'n',
+                (true, false) => 'f',
+                (false, true) => 'b',
+                (true, true) => 'x',
+            };
+            runs.push_str(&format!("{}{}", count, code));
+        }
+        format!("{} {} {} {} {} {}", FORMAT, self.version, self.level, self.rate, ending, runs)
+    }
+
+    /// Reads text written by `encode`. Recordings from other game versions, with an invalid rate
+    /// or longer than `MAX_STEPS` are rejected
+    pub fn decode(text: &str) -> Option<Self> {
+        let mut fields = text.split_whitespace();
+        if fields.next()? != FORMAT {
+            return None;
+        }
+        let version = fields.next()?.to_string();
+        if version != env!("CARGO_PKG_VERSION") {
+            return None;
+        }
+        let level = fields.next()?.parse().ok()?;
+        let rate: f64 = fields.next()?.parse().ok()?;
+        if !rate.is_finite() || rate <= 0.0 {
+            return None;
+        }
+        let ending = match fields.next()? {
+            "c" => Some(Ending::Crash),
+            "w" => Some(Ending::Win),
+            "-" => None,
+            _ => return None,
+        };
+        let mut steps = Vec::new();
+        let mut count = String::new();
+        for c in fields.next().unwrap_or("").chars() {
+            let step = match c {
+                '0'..='9' => {
+                    count.push(c);
+                    continue;
+                },
+                'n' => (false, false),
+                'f' => (true, false),
+                'b' => (false, true),
+                'x' => (true, true),
+                _ => return None,
+            };
+            let count: usize = std::mem::take(&mut count).parse().ok()?;
+            if steps.len().checked_add(count)? > MAX_STEPS {
+                return None;
+            }
+            steps.extend(std::iter::repeat_n(step, count));
+        }
+        if !count.is_empty() {
+            return None;
+        }
+        Some(Recording {
+            level,
+            version,
+            rate,
+            ending,
+            steps,
+        })
+    }
+}
+
+/// Ending of the run being played in the world, once it is decided
+pub fn ending(world: &World) -> Option<Ending> {
+    world.query::<&Finish>().iter().find_map(|(_id, finish)| {
+        if finish.crash {
+            Some(Ending::Crash)
+        } else if finish.reached {
+            Some(Ending::Win)
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,96 @@
+use hecs::World;
+
+use crate::{Finish, ImageStore};
+use crate::dynamics::*;
+use crate::input::*;
+use crate::landing::system_landed;
+use crate::levels::Levels;
+use crate::objectives::system_objectives;
+use crate::passengers::system_passengers;
+use crate::replay::{Recording, ending};
+use crate::timestep::PHYSICS_RATE;
+use crate::transform::system_attach;
+
+/// Runs every gameplay system for one fixed physics step. Shared by the browser loop and headless runs
+pub fn system_step(world: &mut World, input: &Input, delta: f64) {
+    system_previous_position(world);
+    system_orbit(world, delta);
+    system_spacecraft_input(world, input, delta);
+    system_gravity(world, delta);
+    system_attach(world);
+    system_drag(world, delta);
+    system_landed(world, input, delta);
+    system_crash(world, delta);
+    system_reach(world, delta);
+    system_passengers(world, delta);
+    system_objectives(world, delta);
+}
+
+/// Whether the level was won or lost, after which the physics stops stepping
+pub fn level_over(world: &World) -> bool {
+    world.query::<&Finish>().iter().any(|(_id, finish)| finish.crash || finish.reached)
+}
+
+/// A level running without any frontend, stepped at the fixed physics rate
+pub struct Simulation {
+    pub world: World,
+    pub store: ImageStore,
+    pub step: f64,
+    pub time: f64,
+    pub recording: Recording,
+}
+
+impl Simulation {
+    pub fn new(level: i32) -> Self {
+        Simulation::with_levels(&Levels::builtin(), level)
+    }
+
+    pub fn with_levels(levels: &Levels, level: i32) -> Self {
+        let mut store = ImageStore::new();
+        let world = levels.load_level(level, &mut store);
+        Simulation {
+            world,
+            store,
+            step: 1.0 / PHYSICS_RATE,
+            time: 0.0,
+            recording: Recording::new(level, PHYSICS_RATE),
+        }
+    }
+
+    /// Plays back a recording from the start of its level in `levels` until its last step or the end of the run
+    pub fn replay(levels: &Levels, recording: &Recording) -> Self {
+        let mut simulation = Simulation::with_levels(levels, recording.level);
+        simulation.step = 1.0 / recording.rate;
+        simulation.recording.rate = recording.rate;
+        for step in 0..recording.steps.len() {
+            if level_over(&simulation.world) {
+                break;
+            }
+            simulation.step(&recording.input(step));
+        }
+        simulation
+    }
+
+    pub fn step(&mut self, input: &Input) {
+        system_step(&mut self.world, input, self.step);
+        self.recording.push(input);
+        self.recording.ending = ending(&self.world);
+        self.time += self.step;
+    }
+
+    /// Steps with the same input until the level is won, lost or `seconds` have passed
+    pub fn run(&mut self, input: &Input, seconds: f64) {
+        let end = self.time + seconds;
+        while self.time < end && !level_over(&self.world) {
+            self.step(input);
+        }
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.world.query::<&Finish>().iter().any(|(_id, finish)| finish.crash)
+    }
+
+    pub fn reached(&self) -> bool {
+        self.world.query::<&Finish>().iter().any(|(_id, finish)| finish.reached && !finish.crash)
+    }
+}
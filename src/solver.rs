@@ -0,0 +1,123 @@
+use crate::{EndZone, SpaceShip};
+use crate::dynamics::Position;
+use crate::input::Input;
+use crate::levels::Levels;
+use crate::replay::Recording;
+use crate::simulation::Simulation;
+use crate::timestep::PHYSICS_RATE;
+
+/// Inputs the solver can switch to at the start of a segment: (forward, brake)
+const BURNS: [(bool, bool); 2] = [(true, false), (false, true)];
+
+pub struct SolverOptions {
+    /// Candidates kept after every segment
+    pub beam: usize,
+    /// Physics steps during which an input is held
+    pub segment: usize,
+    pub max_seconds: f64,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            beam: 12,
+            segment: 30,
+            max_seconds: 15.0,
+        }
+    }
+}
+
+pub struct Solution {
+    pub fuel: f64,
+    pub recording: Recording,
+}
+
+enum Evaluation {
+    Crash,
+    Win(Solution),
+    /// Closest approach to the end zone when coasting, and fuel left
+    Flying(f64, f64),
+}
+
+fn fuel(simulation: &Simulation) -> (f64, f64) {
+    simulation.world.query::<&SpaceShip>().iter().map(|(_id, spaceship)| (spaceship.initial_fuel - spaceship.fuel, spaceship.fuel)).next().unwrap_or((0.0, 0.0))
+}
+
+/// Distance from the spaceship to the centre of the closest end zone
+fn distance_to_zone(simulation: &Simulation) -> f64 {
+    let mut best = f64::INFINITY;
+    for (_id, (_spaceship, position)) in &mut simulation.world.query::<(&SpaceShip, &Position)>() {
+        for (_id, (zone, zone_position)) in &mut simulation.world.query::<(&EndZone, &Position)>() {
+            best = best.min(zone.distance(zone_position, position));
+        }
+    }
+    best
+}
+
+/// Replays the inputs and then lets the spaceship coast until the time limit, as any later burn starts from there
+fn evaluate(levels: &Levels, recording: &Recording, options: &SolverOptions) -> Evaluation {
+    let mut simulation = Simulation::replay(levels, recording);
+    if simulation.crashed() {
+        return Evaluation::Crash;
+    }
+    let (used, left) = fuel(&simulation);
+    let mut closest = distance_to_zone(&simulation);
+    let coast = Input::default();
+    while simulation.time < options.max_seconds && !simulation.crashed() && !simulation.reached() {
+        simulation.step(&coast);
+        closest = closest.min(distance_to_zone(&simulation));
+    }
+    if simulation.reached() {
+        Evaluation::Win(Solution {
+            fuel: used,
+            recording: simulation.recording,
+        })
+    } else {
+        Evaluation::Flying(closest, left)
+    }
+}
+
+/// Beam search over thrust and brake schedules, replaying every candidate with the real physics.
+/// Returns the winning run of `level` in `levels` that used the least fuel, if any was found.
+pub fn solve(levels: &Levels, level: i32, options: &SolverOptions) -> Option<Solution> {
+    let depth = (options.max_seconds * PHYSICS_RATE) as usize / options.segment;
+    let start = Recording::new(level, PHYSICS_RATE);
+    let mut best: Option<Solution> = None;
+    let mut beam = match evaluate(levels, &start, options) {
+        Evaluation::Crash => Vec::new(),
+        Evaluation::Win(solution) => return Some(solution),
+        Evaluation::Flying(closest, left) => vec![(closest, left, start)],
+    };
+
+    for _ in 0..depth {
+        let mut candidates = Vec::new();
+        for (closest, left, parent) in beam {
+            if left <= 0.0 {
+                continue;
+            }
+            for (forward, brake) in BURNS.iter() {
+                let mut child = parent.clone();
+                child.steps.extend(std::iter::repeat_n((*forward, *brake), options.segment));
+                match evaluate(levels, &child, options) {
+                    Evaluation::Crash => (),
+                    Evaluation::Win(solution) => {
+                        if best.as_ref().is_none_or(|best| solution.fuel < best.fuel) {
+                            best = Some(solution);
+                        }
+                    },
+                    Evaluation::Flying(closest, left) => candidates.push((closest, left, child)),
+                }
+            }
+            // Waiting a segment changes nothing until a later burn, so it keeps the parent score
+            let mut child = parent;
+            child.steps.extend(std::iter::repeat_n((false, false), options.segment));
+            candidates.push((closest, left, child));
+        }
+        if candidates.is_empty() {
+            break;
+        }
+        candidates.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+        beam = candidates.into_iter().take(options.beam).collect();
+    }
+    best
+}
@@ -0,0 +1,44 @@
+pub const PHYSICS_RATE: f64 = 120.0;
+pub const MAX_SUBSTEPS: u32 = 8;
+
+pub struct Timestep {
+    pub step: f64,
+    pub max_substeps: u32,
+    accumulator: f64,
+}
+
+impl Timestep {
+    pub fn new(rate: f64, max_substeps: u32) -> Self {
+        Timestep {
+            step: 1.0 / rate,
+            max_substeps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the frame time to the accumulator and returns how many fixed steps must be simulated.
+    /// Time that does not fit in `max_substeps` steps (e.g. after a tab switch) is dropped.
+    pub fn advance(&mut self, delta: f64) -> u32 {
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_substeps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps == self.max_substeps {
+            self.accumulator = f64::min(self.accumulator, self.step);
+        }
+        steps
+    }
+
+    /// Fraction of a step left in the accumulator, used to interpolate between physics states
+    pub fn alpha(&self) -> f64 {
+        f64::min(self.accumulator / self.step, 1.0)
+    }
+}
+
+impl Default for Timestep {
+    fn default() -> Self {
+        Timestep::new(PHYSICS_RATE, MAX_SUBSTEPS)
+    }
+}
@@ -0,0 +1,87 @@
+use hecs::{With, World};
+
+use crate::{SpaceShip, Finish};
+use crate::collision::segment_circle;
+use crate::dynamics::*;
+use crate::renderer::Renderer;
+use crate::transform::{Attached, system_attach};
+
+/// Only one of every `POINT_EVERY` simulated steps is drawn, giving a dotted line
+const POINT_EVERY: u32 = 6;
+
+/// Predicted path of the spaceship, drawn by the `PathRenderer` of the same entity
+pub struct Trajectory {
+    pub seconds: f64,
+}
+
+/// Copies the bodies that take part in the physics into a scratch world, keeping their entity ids
+fn snapshot(world: &World) -> World {
+    let mut scratch = World::new();
+    for (id, (position, celestial, velocity, orbit)) in &mut world.query::<(&Position, &Celestial, Option<&Velocity>, Option<&Orbit>)>() {
+        scratch.spawn_at(id, (position.clone(), celestial.clone()));
+        if let Some(velocity) = velocity {
+            scratch.insert_one(id, velocity.clone()).unwrap();
+        }
+        if let Some(orbit) = orbit {
+            scratch.insert_one(id, orbit.clone()).unwrap();
+        }
+    }
+    for (id, (position, atmosphere, attached)) in &mut world.query::<(&Position, &Atmosphere, &Attached)>() {
+        scratch.spawn_at(id, (position.clone(), atmosphere.clone(), attached.clone()));
+    }
+    for (id, (position, velocity)) in &mut world.query::<With<SpaceShip, (&Position, &Velocity)>>() {
+        scratch.spawn_at(id, (position.clone(), velocity.clone()));
+    }
+    for (_id, physics) in &mut world.query::<&Physics>() {
+        scratch.spawn((physics.clone(),));
+    }
+    scratch
+}
+
+/// Forward-simulates the spaceship with the same systems used by the game loop, stopping at the first crash
+pub fn predict(world: &World, seconds: f64, delta: f64) -> Vec<Position> {
+    let mut scratch = snapshot(world);
+    // A crashed spaceship loses its velocity and is not in the snapshot
+    let ship = match world.query::<With<SpaceShip, (&Position, &Velocity)>>().iter().next() {
+        Some((id, _components)) => id,
+        None => return Vec::new(),
+    };
+    let steps = (seconds / delta) as u32;
+    let mut points = Vec::new();
+    let mut previous = (*scratch.get::<Position>(ship).unwrap()).clone();
+    for step in 1..=steps {
+        system_orbit(&mut scratch, delta);
+        system_gravity(&mut scratch, delta);
+        system_attach(&mut scratch);
+        system_drag(&mut scratch, delta);
+        let position = (*scratch.get::<Position>(ship).unwrap()).clone();
+        let crash = scratch.query::<(&Position, &Celestial)>().iter().any(|(_id, (planet, celestial))| {
+            segment_circle(&previous, &position, planet, celestial.radius).is_some()
+        });
+        if crash {
+            break;
+        }
+        if step % POINT_EVERY == 0 {
+            points.push(position.clone());
+        }
+        previous = position;
+    }
+    points
+}
+
+pub fn system_trajectory(world: &mut World, delta: f64) {
+    let finished = world.query::<&Finish>().iter().any(|(_id, finish)| finish.finish);
+    let previews: Vec<(hecs::Entity, f64)> = world.query::<&Trajectory>().iter().map(|(id, trajectory)| (id, trajectory.seconds)).collect();
+    for (id, seconds) in previews {
+        let predicted = if finished {
+            Vec::new()
+        } else {
+            predict(world, seconds, delta)
+        };
+        if let Ok(mut renderer) = world.get_mut::<Renderer>(id) {
+            if let Renderer::PathRenderer { points, .. } = &mut *renderer {
+                *points = predicted;
+            }
+        }
+    }
+}
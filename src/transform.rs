@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use hecs::{Entity, World};
+
+use crate::dynamics::Position;
+
+/// Keeps an entity at a fixed offset from its parent, e.g. an end zone following a moving planet
+#[derive(Clone)]
+pub struct Attached {
+    pub parent: Entity,
+    pub offset: Position,
+}
+
+/// Parent chains longer than this are treated as cycles
+pub const MAX_CHAIN: usize = 16;
+
+/// Position of `id` after adding the offsets found walking up its parents, given as `child -> (parent, offset)`.
+/// The walk ends at the first entity without a parent, which keeps its own position
+pub fn chained_position(id: Entity, world: &World, offsets: &HashMap<Entity, (Entity, Position)>) -> Option<Position> {
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut current = id;
+    for _ in 0..=MAX_CHAIN {
+        match offsets.get(&current) {
+            Some((parent, offset)) => {
+                x += offset.x;
+                y += offset.y;
+                current = *parent;
+            },
+            None => return world.get::<Position>(current).ok().map(|position| Position {
+                x: position.x + x,
+                y: position.y + y,
+            }),
+        }
+    }
+    None
+}
+
+/// Moves attached entities after their parents. Must run once the parents have moved for the step
+pub fn system_attach(world: &mut World) {
+    let offsets: HashMap<Entity, (Entity, Position)> = world.query::<&Attached>().iter().map(|(id, attached)| {
+        (id, (attached.parent, attached.offset.clone()))
+    }).collect();
+
+    for id in offsets.keys() {
+        if let Some(new_position) = chained_position(*id, world, &offsets) {
+            if let Ok(mut position) = world.get_mut::<Position>(*id) {
+                *position = new_position;
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
 
 pub fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
@@ -31,6 +32,21 @@ pub fn get_local_storage(key: &str) -> String {
     storage.get_item(key).unwrap().unwrap()
 }
 
+pub fn get_local_storage_item(key: &str) -> Option<String> {
+    let storage = window().local_storage().expect("no 'localStorage' found").unwrap();
+    storage.get_item(key).unwrap()
+}
+
+/// Downloads a text file next to the page
+pub async fn fetch_text(url: &str) -> Option<String> {
+    let response = JsFuture::from(window().fetch_with_str(url)).await.ok()?;
+    let response: web_sys::Response = response.dyn_into().ok()?;
+    if !response.ok() {
+        return None;
+    }
+    JsFuture::from(response.text().ok()?).await.ok()?.as_string()
+}
+
 pub fn go_web(url: &str) {
     window().location().set_href(url).expect("error in 'window.location.href'");
 }
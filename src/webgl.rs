@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext as GL, WebGlBuffer, WebGlProgram, WebGlShader, WebGlTexture, WebGlVertexArrayObject};
+
+use crate::ImageStore;
+use crate::backend::Backend;
+use crate::batch::{Atlas, Batch, Label, Run, QUAD_FLOATS, CIRCLE_FLOATS};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::web::{document, log};
+
+const ATLAS_SIZE: u32 = 2048;
+
+const QUAD_VERTEX: &str = r#"#version 300 es
+uniform vec2 u_screen;
+in vec2 a_position;
+in vec2 a_uv;
+in vec4 a_color;
+out vec2 v_uv;
+out vec4 v_color;
+void main() {
+    v_uv = a_uv;
+    v_color = a_color;
+    vec2 clip = a_position / u_screen * 2.0 - 1.0;
+    gl_Position = vec4(clip.x, -clip.y, 0.0, 1.0);
+}
+"#;
+
+const QUAD_FRAGMENT: &str = r#"#version 300 es
+precision mediump float;
+uniform sampler2D u_texture;
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 color;
+void main() {
+    color = v_uv.x < 0.0 ? v_color : texture(u_texture, v_uv) * v_color;
+}
+"#;
+
+const CIRCLE_VERTEX: &str = r#"#version 300 es
+uniform vec2 u_screen;
+in vec2 a_corner;
+in vec2 a_center;
+in vec2 a_axis_x;
+in vec2 a_axis_y;
+in float a_inner;
+in vec4 a_color;
+out vec2 v_local;
+out float v_inner;
+out vec4 v_color;
+void main() {
+    v_local = a_corner;
+    v_inner = a_inner;
+    v_color = a_color;
+    vec2 position = a_center + a_corner.x * a_axis_x + a_corner.y * a_axis_y;
+    vec2 clip = position / u_screen * 2.0 - 1.0;
+    gl_Position = vec4(clip.x, -clip.y, 0.0, 1.0);
+}
+"#;
+
+const CIRCLE_FRAGMENT: &str = r#"#version 300 es
+precision mediump float;
+in vec2 v_local;
+in float v_inner;
+in vec4 v_color;
+out vec4 color;
+void main() {
+    float distance = length(v_local);
+    if (distance > 1.0 || distance < v_inner) {
+        discard;
+    }
+    color = v_color;
+}
+"#;
+
+/// Two triangles covering the unit square around the origin, shared by every circle instance
+const CORNERS: [f32; 12] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0, 1.0, -1.0, 1.0];
+
+/// Quad vertices covering the whole screen with a whole texture, used to lay text over the frame
+fn screen_quad() -> [f32; 6 * QUAD_FLOATS] {
+    let (w, h) = (SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    let mut vertices = [0.0; 6 * QUAD_FLOATS];
+    for (i, (x, y)) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter().enumerate() {
+        vertices[i * QUAD_FLOATS..(i + 1) * QUAD_FLOATS].copy_from_slice(&[x * w, y * h, *x, *y, 1.0, 1.0, 1.0, 1.0]);
+    }
+    vertices
+}
+
+/// Draws a `Batch` with WebGL2: one call per run. Every run of text is drawn on a hidden 2D canvas
+/// that is copied as a texture over what was drawn before it
+pub struct WebGl {
+    gl: GL,
+    pub batch: Batch,
+    quad_program: WebGlProgram,
+    circle_program: WebGlProgram,
+    quad_vao: WebGlVertexArrayObject,
+    circle_vao: WebGlVertexArrayObject,
+    screen_vao: WebGlVertexArrayObject,
+    quad_buffer: WebGlBuffer,
+    instance_buffer: WebGlBuffer,
+    atlas_texture: WebGlTexture,
+    /// Textures of the images too big for the atlas, by atlas page
+    pages: HashMap<u32, WebGlTexture>,
+    text_context: web_sys::CanvasRenderingContext2d,
+    /// Texture of each run of text in the frame, with the labels it holds. It is only drawn again when they change
+    text_textures: Vec<(WebGlTexture, Vec<Label>)>,
+}
+
+fn compile(gl: &GL, kind: u32, source: &str) -> Option<WebGlShader> {
+    let shader = gl.create_shader(kind)?;
+    gl.shader_source(&shader, source);
+    gl.compile_shader(&shader);
+    if gl.get_shader_parameter(&shader, GL::COMPILE_STATUS).as_bool().unwrap_or(false) {
+        Some(shader)
+    } else {
+        log(&format!("WebGL shader error: {}", gl.get_shader_info_log(&shader).unwrap_or_default()));
+        None
+    }
+}
+
+fn link(gl: &GL, vertex: &str, fragment: &str) -> Option<WebGlProgram> {
+    let program = gl.create_program()?;
+    gl.attach_shader(&program, &compile(gl, GL::VERTEX_SHADER, vertex)?);
+    gl.attach_shader(&program, &compile(gl, GL::FRAGMENT_SHADER, fragment)?);
+    gl.link_program(&program);
+    if gl.get_program_parameter(&program, GL::LINK_STATUS).as_bool().unwrap_or(false) {
+        Some(program)
+    } else {
+        log(&format!("WebGL program error: {}", gl.get_program_info_log(&program).unwrap_or_default()));
+        None
+    }
+}
+
+/// Points a float attribute of `program` at the bound buffer. Instanced attributes advance once per instance
+fn attribute(gl: &GL, program: &WebGlProgram, name: &str, size: i32, stride: usize, offset: usize, instanced: bool) {
+    let location = gl.get_attrib_location(program, name);
+    if location < 0 {
+        return;
+    }
+    let location = location as u32;
+    gl.enable_vertex_attrib_array(location);
+    gl.vertex_attrib_pointer_with_i32(location, size, GL::FLOAT, false, (stride * 4) as i32, (offset * 4) as i32);
+    if instanced {
+        gl.vertex_attrib_divisor(location, 1);
+    }
+}
+
+fn upload(gl: &GL, target: u32, data: &[f32], usage: u32) {
+    let array = js_sys::Float32Array::from(data);
+    gl.buffer_data_with_array_buffer_view(target, &array, usage);
+}
+
+fn texture(gl: &GL) -> Option<WebGlTexture> {
+    let texture = gl.create_texture()?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    Some(texture)
+}
+
+impl WebGl {
+    /// `None` if the browser has no WebGL2 or the shaders do not build
+    pub fn new(canvas: &web_sys::HtmlCanvasElement) -> Option<Self> {
+        let gl = canvas.get_context("webgl2").ok()??.dyn_into::<GL>().ok()?;
+        let quad_program = link(&gl, QUAD_VERTEX, QUAD_FRAGMENT)?;
+        let circle_program = link(&gl, CIRCLE_VERTEX, CIRCLE_FRAGMENT)?;
+
+        let quad_vao = gl.create_vertex_array()?;
+        gl.bind_vertex_array(Some(&quad_vao));
+        let quad_buffer = gl.create_buffer()?;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&quad_buffer));
+        attribute(&gl, &quad_program, "a_position", 2, QUAD_FLOATS, 0, false);
+        attribute(&gl, &quad_program, "a_uv", 2, QUAD_FLOATS, 2, false);
+        attribute(&gl, &quad_program, "a_color", 4, QUAD_FLOATS, 4, false);
+
+        let circle_vao = gl.create_vertex_array()?;
+        gl.bind_vertex_array(Some(&circle_vao));
+        let corner_buffer = gl.create_buffer()?;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&corner_buffer));
+        upload(&gl, GL::ARRAY_BUFFER, &CORNERS, GL::STATIC_DRAW);
+        attribute(&gl, &circle_program, "a_corner", 2, 2, 0, false);
+        let instance_buffer = gl.create_buffer()?;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&instance_buffer));
+
+        let screen_vao = gl.create_vertex_array()?;
+        gl.bind_vertex_array(Some(&screen_vao));
+        let screen_buffer = gl.create_buffer()?;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&screen_buffer));
+        upload(&gl, GL::ARRAY_BUFFER, &screen_quad(), GL::STATIC_DRAW);
+        attribute(&gl, &quad_program, "a_position", 2, QUAD_FLOATS, 0, false);
+        attribute(&gl, &quad_program, "a_uv", 2, QUAD_FLOATS, 2, false);
+        attribute(&gl, &quad_program, "a_color", 4, QUAD_FLOATS, 4, false);
+        gl.bind_vertex_array(None);
+
+        let atlas_texture = texture(&gl)?;
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            GL::TEXTURE_2D, 0, GL::RGBA as i32, ATLAS_SIZE as i32, ATLAS_SIZE as i32, 0, GL::RGBA, GL::UNSIGNED_BYTE, None,
+        ).ok()?;
+
+        let text_canvas = document().create_element("canvas").ok()?.dyn_into::<web_sys::HtmlCanvasElement>().ok()?;
+        text_canvas.set_width(SCREEN_WIDTH as u32);
+        text_canvas.set_height(SCREEN_HEIGHT as u32);
+        let text_context = text_canvas.get_context("2d").ok()??.dyn_into::<web_sys::CanvasRenderingContext2d>().ok()?;
+
+        gl.enable(GL::BLEND);
+        // Source-over for the colour, and destination alpha kept as coverage so the page does not show through
+        // the premultiplied canvas where translucent shapes are drawn
+        gl.blend_func_separate(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA, GL::ONE, GL::ONE_MINUS_SRC_ALPHA);
+        gl.viewport(0, 0, canvas.width() as i32, canvas.height() as i32);
+
+        Some(WebGl {
+            gl,
+            batch: Batch::new(Atlas::new(ATLAS_SIZE)),
+            quad_program,
+            circle_program,
+            quad_vao,
+            circle_vao,
+            screen_vao,
+            quad_buffer,
+            instance_buffer,
+            atlas_texture,
+            pages: HashMap::new(),
+            text_context,
+            text_textures: Vec::new(),
+        })
+    }
+
+    /// Copies the images that finished loading into the atlas. Images that do not fit get a texture of their own
+    pub fn upload_images(&mut self, store: &ImageStore) {
+        for (name, image) in store {
+            if self.batch.atlas.get(name).is_some() || !image.complete() || image.natural_width() == 0 {
+                continue;
+            }
+            let (width, height) = (image.natural_width(), image.natural_height());
+            let region = match self.batch.atlas.insert(name, width, height) {
+                Some(region) => region,
+                None => {
+                    log(&format!("Image {} does not fit in the sprite atlas, it gets a texture of its own", name));
+                    self.batch.atlas.insert_alone(name, width, height)
+                },
+            };
+            if region.page == 0 {
+                self.gl.bind_texture(GL::TEXTURE_2D, Some(&self.atlas_texture));
+                self.gl.tex_sub_image_2d_with_u32_and_u32_and_html_image_element(
+                    GL::TEXTURE_2D, 0, region.x as i32, region.y as i32, GL::RGBA, GL::UNSIGNED_BYTE, image,
+                ).unwrap();
+            } else if let Some(page) = texture(&self.gl) {
+                self.gl.tex_image_2d_with_u32_and_u32_and_html_image_element(
+                    GL::TEXTURE_2D, 0, GL::RGBA as i32, GL::RGBA, GL::UNSIGNED_BYTE, image,
+                ).unwrap();
+                self.pages.insert(region.page, page);
+            }
+        }
+    }
+
+    fn use_program(&self, program: &WebGlProgram) {
+        self.gl.use_program(Some(program));
+        if let Some(screen) = self.gl.get_uniform_location(program, "u_screen") {
+            self.gl.uniform2f(Some(&screen), SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        }
+    }
+
+    /// Draws the labels of the `index`th text run on the hidden canvas and uploads it to the run texture
+    /// when they changed since the last frame
+    fn update_text(&mut self, index: usize, start: usize, count: usize) -> Option<()> {
+        let labels = &self.batch.labels[start..start + count];
+        if index == self.text_textures.len() {
+            self.text_textures.push((texture(&self.gl)?, Vec::new()));
+        }
+        let (texture, uploaded) = &mut self.text_textures[index];
+        if uploaded.as_slice() == labels {
+            return Some(());
+        }
+        let context = &self.text_context;
+        context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        context.clear_rect(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        for label in labels {
+            let t = label.transform;
+            context.set_transform(t.a, t.b, t.c, t.d, t.e, t.f).unwrap();
+            context.set_font(&label.font);
+            context.set_fill_style(&JsValue::from_str(&label.color));
+            context.fill_text(&label.text, 0.0, 0.0).unwrap();
+        }
+        self.gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+        self.gl.tex_image_2d_with_u32_and_u32_and_html_canvas_element(
+            GL::TEXTURE_2D, 0, GL::RGBA as i32, GL::RGBA, GL::UNSIGNED_BYTE, &context.canvas().unwrap(),
+        ).unwrap();
+        *uploaded = labels.to_vec();
+        Some(())
+    }
+
+    /// Draws the frame collected in `batch` and starts the next one
+    pub fn draw(&mut self) {
+        let gl = self.gl.clone();
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(GL::COLOR_BUFFER_BIT);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_buffer));
+        upload(&gl, GL::ARRAY_BUFFER, &self.batch.quads, GL::STREAM_DRAW);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instance_buffer));
+        upload(&gl, GL::ARRAY_BUFFER, &self.batch.circles, GL::STREAM_DRAW);
+        gl.active_texture(GL::TEXTURE0);
+
+        let mut text_runs = 0;
+        for run in self.batch.runs.clone() {
+            match run {
+                Run::Quads { start, count, page } => {
+                    let texture = if page == 0 { Some(&self.atlas_texture) } else { self.pages.get(&page) };
+                    self.use_program(&self.quad_program);
+                    gl.bind_vertex_array(Some(&self.quad_vao));
+                    gl.bind_texture(GL::TEXTURE_2D, texture);
+                    gl.draw_arrays(GL::TRIANGLES, start as i32, count as i32);
+                },
+                Run::Circles { start, count } => {
+                    self.use_program(&self.circle_program);
+                    gl.bind_vertex_array(Some(&self.circle_vao));
+                    // WebGL2 has no base instance, so the instance attributes are pointed at the run instead
+                    gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.instance_buffer));
+                    let offset = start * CIRCLE_FLOATS;
+                    attribute(&gl, &self.circle_program, "a_center", 2, CIRCLE_FLOATS, offset, true);
+                    attribute(&gl, &self.circle_program, "a_axis_x", 2, CIRCLE_FLOATS, offset + 2, true);
+                    attribute(&gl, &self.circle_program, "a_axis_y", 2, CIRCLE_FLOATS, offset + 4, true);
+                    attribute(&gl, &self.circle_program, "a_inner", 1, CIRCLE_FLOATS, offset + 6, true);
+                    attribute(&gl, &self.circle_program, "a_color", 4, CIRCLE_FLOATS, offset + 7, true);
+                    gl.draw_arrays_instanced(GL::TRIANGLES, 0, 6, count as i32);
+                },
+                Run::Text { start, count } => {
+                    if self.update_text(text_runs, start, count).is_none() {
+                        continue;
+                    }
+                    self.use_program(&self.quad_program);
+                    gl.bind_vertex_array(Some(&self.screen_vao));
+                    gl.bind_texture(GL::TEXTURE_2D, Some(&self.text_textures[text_runs].0));
+                    gl.draw_arrays(GL::TRIANGLES, 0, 6);
+                    text_runs += 1;
+                },
+            }
+        }
+        gl.bind_vertex_array(None);
+        self.batch.clear();
+    }
+}
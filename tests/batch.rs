@@ -0,0 +1,138 @@
+use orbital_bus::ImageStore;
+use orbital_bus::backend::Backend;
+use orbital_bus::batch::{Atlas, Batch, Region, Run, CIRCLE_FLOATS, QUAD_FLOATS};
+use orbital_bus::dynamics::Position;
+use orbital_bus::levels::Levels;
+use orbital_bus::renderer::system_renderer;
+
+const ORIGIN: Position = Position { x: 0.0, y: 0.0 };
+
+#[test]
+fn atlas_packs_images_in_rows() {
+    let mut atlas = Atlas::new(64);
+    assert_eq!(atlas.insert("a", 30, 10), Some(Region { page: 0, x: 1, y: 1, width: 30, height: 10 }));
+    assert_eq!(atlas.insert("b", 20, 20), Some(Region { page: 0, x: 33, y: 1, width: 20, height: 20 }));
+    // No room left in the first row
+    assert_eq!(atlas.insert("c", 20, 5), Some(Region { page: 0, x: 1, y: 23, width: 20, height: 5 }));
+    assert_eq!(atlas.insert("a", 30, 10), atlas.get("a"));
+    assert_eq!(atlas.insert("huge", 64, 1), None);
+    assert_eq!(atlas.insert("tall", 10, 50), None);
+}
+
+#[test]
+fn failed_inserts_leave_the_atlas_as_it_was() {
+    let mut atlas = Atlas::new(64);
+    atlas.insert("a", 30, 10).unwrap();
+    // Would need a new row that is too low for it
+    assert_eq!(atlas.insert("wide", 40, 60), None);
+    assert_eq!(atlas.insert("b", 20, 20), Some(Region { page: 0, x: 33, y: 1, width: 20, height: 20 }));
+}
+
+#[test]
+fn images_that_do_not_fit_get_their_own_page() {
+    let mut atlas = Atlas::new(64);
+    atlas.insert("ship.png", 30, 14).unwrap();
+    assert_eq!(atlas.insert("space.png", 100, 100), None);
+    let region = atlas.insert_alone("space.png", 100, 100);
+    assert_eq!(region, Region { page: 1, x: 0, y: 0, width: 100, height: 100 });
+    assert_eq!(atlas.get("space.png"), Some(region));
+    assert_eq!(atlas.insert_alone("space.png", 100, 100), region);
+
+    let mut batch = Batch::new(atlas);
+    batch.image("space.png", &ORIGIN, 100.0, 100.0);
+    batch.rect(&ORIGIN, 10.0, 10.0, "red");
+    batch.image("ship.png", &ORIGIN, 30.0, 14.0);
+    // The whole page is sampled, and untextured quads join any page
+    assert_eq!(&batch.quads[..QUAD_FLOATS], &[0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+    assert_eq!(batch.runs, vec![
+        Run::Quads { start: 0, count: 12, page: 1 },
+        Run::Quads { start: 12, count: 6, page: 0 },
+    ]);
+}
+
+#[test]
+fn runs_keep_the_drawing_order() {
+    let mut batch = Batch::new(Atlas::new(64));
+    batch.rect(&ORIGIN, 10.0, 10.0, "red");
+    batch.rect(&ORIGIN, 10.0, 10.0, "white");
+    batch.circle(&ORIGIN, 5.0, "red");
+    batch.ring(&ORIGIN, 2.0, 5.0, "red");
+    batch.rect(&ORIGIN, 10.0, 10.0, "red");
+    assert_eq!(batch.runs, vec![
+        Run::Quads { start: 0, count: 12, page: 0 },
+        Run::Circles { start: 0, count: 2 },
+        Run::Quads { start: 12, count: 6, page: 0 },
+    ]);
+    assert_eq!(batch.quads.len(), 18 * QUAD_FLOATS);
+    assert_eq!(batch.circles.len(), 2 * CIRCLE_FLOATS);
+    batch.clear();
+    assert!(batch.runs.is_empty() && batch.quads.is_empty() && batch.circles.is_empty());
+}
+
+#[test]
+fn circles_carry_the_transform() {
+    let mut batch = Batch::new(Atlas::new(64));
+    batch.translate(100.0, 50.0);
+    batch.scale(2.0, 2.0);
+    batch.ring(&Position { x: 10.0, y: 0.0 }, 5.0, 10.0, "rgba(255, 0, 0, 0.5)");
+    let expected = [120.0, 50.0, 20.0, 0.0, 0.0, 20.0, 0.5, 1.0, 0.0, 0.0, 0.5];
+    for (value, expected) in batch.circles.iter().zip(expected.iter()) {
+        assert!((value - expected).abs() < 0.01, "{:?}", batch.circles);
+    }
+}
+
+#[test]
+fn images_need_the_atlas() {
+    let mut atlas = Atlas::new(64);
+    atlas.insert("ship.png", 30, 14);
+    let mut batch = Batch::new(atlas);
+    batch.image("missing.png", &ORIGIN, 10.0, 10.0);
+    assert!(batch.quads.is_empty());
+    assert_eq!(batch.image_size("ship.png"), Some((30.0, 14.0)));
+    batch.image("ship.png", &ORIGIN, 30.0, 14.0);
+    // First vertex: top left of the screen and of the image in the atlas, untinted
+    assert_eq!(&batch.quads[..QUAD_FLOATS], &[0.0, 0.0, 1.0 / 64.0, 1.0 / 64.0, 1.0, 1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn text_is_kept_as_labels() {
+    let mut batch = Batch::new(Atlas::new(64));
+    batch.translate(5.0, 5.0);
+    batch.text("Success!", &Position { x: 10.0, y: 20.0 }, "40px Tsoonami", "white");
+    assert_eq!(batch.labels.len(), 1);
+    assert_eq!(batch.labels[0].transform.apply(0.0, 0.0), (15.0, 25.0));
+    assert_eq!(batch.runs, vec![Run::Text { start: 0, count: 1 }]);
+}
+
+#[test]
+fn text_keeps_its_place_in_the_drawing_order() {
+    let mut batch = Batch::new(Atlas::new(64));
+    batch.rect(&ORIGIN, 10.0, 10.0, "red");
+    batch.text("Fuel", &ORIGIN, "20px Tsoonami", "white");
+    batch.text("Aboard", &ORIGIN, "20px Tsoonami", "white");
+    batch.rect(&ORIGIN, 10.0, 10.0, "#1b1b1b");
+    batch.text("Success!", &ORIGIN, "40px Tsoonami", "white");
+    assert_eq!(batch.runs, vec![
+        Run::Quads { start: 0, count: 6, page: 0 },
+        Run::Text { start: 0, count: 2 },
+        Run::Quads { start: 6, count: 6, page: 0 },
+        Run::Text { start: 2, count: 1 },
+    ]);
+}
+
+#[test]
+fn a_level_is_drawn_in_few_calls() {
+    let mut store = ImageStore::new();
+    let mut world = Levels::builtin().load_level(1, &mut store);
+    let mut atlas = Atlas::new(2048);
+    let mut names: Vec<&String> = store.keys().collect();
+    names.sort();
+    for name in names {
+        atlas.insert(name, 64, 64).unwrap();
+    }
+    let mut batch = Batch::new(atlas);
+    system_renderer(&mut world, &mut batch, 1.0);
+    let drawn = batch.quads.len() / QUAD_FLOATS / 6 + batch.circles.len() / CIRCLE_FLOATS;
+    assert!(drawn > 5);
+    assert!(batch.runs.len() < drawn);
+}
@@ -0,0 +1,162 @@
+use hecs::World;
+
+use orbital_bus::{ImageStore, SpaceShip};
+use orbital_bus::dynamics::PreviousPosition;
+use orbital_bus::hud::{CameraHUD, system_hud};
+use orbital_bus::input::Input;
+use orbital_bus::levels::Levels;
+use orbital_bus::renderer::{Camera, CameraMode, Renderer, SCREEN_HEIGHT, SCREEN_WIDTH, system_offset, system_zoom};
+
+mod common;
+
+/// The wide test level, zooming from 0.1 to 3.0, with more World fields from `extra`
+fn wide(extra: &str) -> String {
+    common::pack(50.0, 0.0, &format!("{}\n        zoom: (min: 0.1, max: 3.0),{}", common::WIDE, extra))
+}
+
+fn camera(world: &World) -> (f64, f64, f64) {
+    let mut query = world.query::<&Camera>();
+    let (_id, camera) = query.iter().next().unwrap();
+    (camera.zoom, camera.offset.x, camera.offset.y)
+}
+
+#[test]
+fn levels_that_fit_keep_their_framing() {
+    let mut store = ImageStore::new();
+    let world = Levels::builtin().load_level(1, &mut store);
+    assert_eq!(camera(&world), (1.0, 0.0, 0.0));
+}
+
+#[test]
+fn large_levels_start_zoomed_out() {
+    let world = common::load(&wide(""));
+    let (zoom, x, _y) = camera(&world);
+    assert!((zoom - SCREEN_WIDTH / 4100.0).abs() < 1e-9);
+    // Both planets end up on screen
+    assert!(-1050.0 * zoom + x >= -1e-9);
+    assert!(3050.0 * zoom + x <= SCREEN_WIDTH + 1e-9);
+}
+
+#[test]
+fn zoom_is_clamped_and_keeps_the_centre() {
+    let mut world = common::load(&wide(""));
+    let (zoom, x, y) = camera(&world);
+    let center = ((SCREEN_WIDTH / 2.0 - x) / zoom, (SCREEN_HEIGHT / 2.0 - y) / zoom);
+
+    let mut input = Input { zoom: 100.0, ..Input::default() };
+    system_zoom(&mut world, &mut input);
+    let (zoom, x, y) = camera(&world);
+    assert_eq!(zoom, 3.0);
+    assert_eq!(input.zoom, 0.0);
+    assert!(((SCREEN_WIDTH / 2.0 - x) / zoom - center.0).abs() < 1e-6);
+    assert!(((SCREEN_HEIGHT / 2.0 - y) / zoom - center.1).abs() < 1e-6);
+}
+
+fn load(mode: &str) -> World {
+    common::load(&wide(&format!("\n        camera: {},", mode)))
+}
+
+fn mode(world: &World) -> CameraMode {
+    let mut query = world.query::<&Camera>();
+    let (_id, camera) = query.iter().next().unwrap();
+    camera.mode
+}
+
+#[test]
+fn levels_choose_the_starting_mode() {
+    let mut store = ImageStore::new();
+    let world = Levels::builtin().load_level(1, &mut store);
+    assert_eq!(mode(&world), CameraMode::Edge);
+    assert_eq!(mode(&load("Frame")), CameraMode::Frame);
+}
+
+#[test]
+fn camera_key_cycles_modes() {
+    let mut world = load("Edge");
+    let mut input = Input::default();
+    for expected in [CameraMode::Follow, CameraMode::Frame, CameraMode::Free, CameraMode::Edge] {
+        input.camera = true;
+        system_offset(&mut world, &mut input, 0.0, 1.0);
+        assert!(!input.camera);
+        assert_eq!(mode(&world), expected);
+    }
+}
+
+#[test]
+fn follow_eases_towards_the_spaceship() {
+    let mut world = load("Follow");
+    let mut input = Input::default();
+    let (zoom, x, _y) = camera(&world);
+    let distance = |x: f64| (SCREEN_WIDTH / 2.0 - x) / zoom;
+    let before = distance(x).abs();
+    system_offset(&mut world, &mut input, 1.0 / 60.0, 1.0);
+    let (_zoom, x, _y) = camera(&world);
+    // Closer to centring the spaceship at the origin, but not there in one frame
+    assert!(distance(x).abs() < before);
+    assert!(distance(x).abs() > 0.0);
+    for _ in 0..600 {
+        system_offset(&mut world, &mut input, 1.0 / 60.0, 1.0);
+    }
+    let (zoom, x, y) = camera(&world);
+    assert!((x - SCREEN_WIDTH / 2.0).abs() < 1e-3);
+    assert!((y - SCREEN_HEIGHT / 2.0).abs() < 1e-3);
+    assert!(zoom > 0.0);
+}
+
+#[test]
+fn follow_tracks_the_interpolated_spaceship() {
+    let mut world = load("Follow");
+    let ship = world.query::<&SpaceShip>().iter().next().map(|(id, _ship)| id).unwrap();
+    world.insert_one(ship, PreviousPosition { x: -100.0, y: 40.0 }).unwrap();
+    let mut input = Input::default();
+    for _ in 0..600 {
+        system_offset(&mut world, &mut input, 1.0 / 60.0, 0.25);
+    }
+    // A quarter of the way from (-100, 40) to the spaceship at the origin, where it is drawn
+    let (zoom, x, y) = camera(&world);
+    assert!((x - (SCREEN_WIDTH / 2.0 + 75.0 * zoom)).abs() < 1e-3);
+    assert!((y - (SCREEN_HEIGHT / 2.0 - 30.0 * zoom)).abs() < 1e-3);
+}
+
+#[test]
+fn frame_keeps_the_spaceship_and_end_zone_in_view() {
+    let mut world = load("Frame");
+    let mut input = Input::default();
+    for _ in 0..600 {
+        system_offset(&mut world, &mut input, 1.0 / 60.0, 1.0);
+    }
+    let (zoom, x, y) = camera(&world);
+    // Spaceship at the origin and end zone centred at (500, 0)
+    for (px, py) in [(0.0, 0.0), (500.0, 0.0)] {
+        let (sx, sy) = (px * zoom + x, py * zoom + y);
+        assert!(sx > 0.0 && sx < SCREEN_WIDTH, "{} outside", sx);
+        assert!(sy > 0.0 && sy < SCREEN_HEIGHT, "{} outside", sy);
+    }
+    assert!(zoom <= 3.0);
+}
+
+#[test]
+fn free_camera_only_moves_when_dragged() {
+    let mut world = load("Free");
+    let mut input = Input::default();
+    let before = camera(&world);
+    system_offset(&mut world, &mut input, 1.0, 1.0);
+    assert_eq!(camera(&world), before);
+    input.pan = (30.0, -20.0);
+    system_offset(&mut world, &mut input, 1.0, 1.0);
+    let (zoom, x, y) = camera(&world);
+    assert_eq!((zoom, x, y), (before.0, before.1 + 30.0, before.2 - 20.0));
+    assert_eq!(input.pan, (0.0, 0.0));
+}
+
+#[test]
+fn hud_shows_the_mode() {
+    let mut world = load("Frame");
+    system_hud(&mut world);
+    let mut query = world.query::<(&Renderer, &CameraHUD)>();
+    let (_id, (renderer, _hud)) = query.iter().next().unwrap();
+    match renderer {
+        Renderer::TextRenderer { text, .. } => assert_eq!(text, "Camera Frame (C)"),
+        _ => panic!("camera readout is not text"),
+    }
+}
@@ -0,0 +1,62 @@
+use orbital_bus::collision::{segment_annulus, segment_circle, segment_rect};
+use orbital_bus::dynamics::Position;
+
+fn at(x: f64, y: f64) -> Position {
+    Position { x, y }
+}
+
+#[test]
+fn circle_hits_misses_and_grazes() {
+    let center = at(0.0, 0.0);
+    let time = segment_circle(&at(-20.0, 0.0), &at(0.0, 0.0), &center, 10.0).unwrap();
+    assert!((time - 0.5).abs() < 1e-9);
+    assert_eq!(segment_circle(&at(-20.0, 20.0), &at(20.0, 20.0), &center, 10.0), None);
+    assert_eq!(segment_circle(&at(-20.0, 10.0), &at(20.0, 10.0), &center, 10.0), None);
+    assert_eq!(segment_circle(&at(-20.0, 0.0), &at(-15.0, 0.0), &center, 10.0), None);
+    assert_eq!(segment_circle(&at(5.0, 0.0), &at(50.0, 0.0), &center, 10.0), Some(0.0));
+}
+
+#[test]
+fn circle_catches_tunnelling() {
+    // Both ends are outside the circle, only the middle of the step goes through it
+    let time = segment_circle(&at(-100.0, 0.0), &at(100.0, 0.0), &at(0.0, 0.0), 10.0).unwrap();
+    assert!((time - 0.45).abs() < 1e-9);
+}
+
+#[test]
+fn rect_hits_misses_and_grazes() {
+    let (min, max) = (at(0.0, 0.0), at(10.0, 10.0));
+    let time = segment_rect(&at(-10.0, 5.0), &at(10.0, 5.0), &min, &max).unwrap();
+    assert!((time - 0.5).abs() < 1e-9);
+    assert_eq!(segment_rect(&at(-10.0, 20.0), &at(20.0, 20.0), &min, &max), None);
+    assert_eq!(segment_rect(&at(-10.0, 10.0), &at(20.0, 10.0), &min, &max), None);
+    assert_eq!(segment_rect(&at(-10.0, -10.0), &at(0.0, 0.0), &min, &max), None);
+    assert_eq!(segment_rect(&at(5.0, 5.0), &at(50.0, 5.0), &min, &max), Some(0.0));
+}
+
+#[test]
+fn rect_catches_tunnelling() {
+    let time = segment_rect(&at(-90.0, 5.0), &at(110.0, 5.0), &at(0.0, 0.0), &at(10.0, 10.0)).unwrap();
+    assert!((time - 0.45).abs() < 1e-9);
+}
+
+#[test]
+fn annulus_hits_misses_and_grazes() {
+    let center = at(0.0, 0.0);
+    let time = segment_annulus(&at(-40.0, 0.0), &at(-20.0, 0.0), &center, 10.0, 30.0).unwrap();
+    assert!((time - 0.5).abs() < 1e-9);
+    assert_eq!(segment_annulus(&at(-40.0, 40.0), &at(40.0, 40.0), &center, 10.0, 30.0), None);
+    assert_eq!(segment_annulus(&at(-40.0, 30.0), &at(40.0, 30.0), &center, 10.0, 30.0), None);
+    // Moving inside the hole never touches the band
+    assert_eq!(segment_annulus(&at(-5.0, 0.0), &at(5.0, 0.0), &center, 10.0, 30.0), None);
+    // Leaving the hole reaches the band at its inner edge
+    let time = segment_annulus(&at(0.0, 0.0), &at(20.0, 0.0), &center, 10.0, 30.0).unwrap();
+    assert!((time - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn annulus_catches_tunnelling() {
+    // Crossing the whole band and the hole in one step still enters at the outer edge
+    let time = segment_annulus(&at(-50.0, 0.0), &at(50.0, 0.0), &at(0.0, 0.0), 10.0, 30.0).unwrap();
+    assert!((time - 0.2).abs() < 1e-9);
+}
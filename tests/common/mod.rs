@@ -0,0 +1,46 @@
+//! Level pack fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use hecs::World;
+
+use orbital_bus::ImageStore;
+use orbital_bus::levels::Levels;
+use orbital_bus::simulation::Simulation;
+
+/// Two planets 4000 pixels apart, too wide to fit the screen, with the spaceship at rest between them
+pub const WIDE: &str = r#"planets: [(ref: "rock", x: -1000.0, y: 0.0), (ref: "rock", x: 3000.0, y: 0.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 0.0, vy: 0.0, fuel: 1.0),
+        end: (x: 500.0, y: 0.0),"#;
+
+/// Pack with a fixed `rock` planet definition and a single level with id 1. `world` holds the rest of its fields
+pub fn pack(radius: f64, mass: f64, world: &str) -> String {
+    format!(r#"Pack(
+    name: "Test",
+    author: "tests",
+    version: "1",
+    planets: [PlanetDef(id: "rock", sprite: "mercury.png", radius: {:?}, mass: {:?}, fix: true)],
+    worlds: [World(
+        id: 1,
+        background: false,
+        {}
+    )],
+)"#, radius, mass, world)
+}
+
+/// Built-in levels with `pack` on top
+pub fn levels(pack: &str) -> Levels {
+    let mut levels = Levels::builtin();
+    levels.add_pack(pack).unwrap();
+    levels
+}
+
+/// Level 1 of `pack`, ready to be drawn
+pub fn load(pack: &str) -> World {
+    let mut store = ImageStore::new();
+    levels(pack).load_level(1, &mut store)
+}
+
+/// Level 1 of `pack`, running headless
+pub fn simulation(pack: &str) -> Simulation {
+    Simulation::with_levels(&levels(pack), 1)
+}
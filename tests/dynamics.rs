@@ -0,0 +1,187 @@
+use hecs::{Entity, World};
+
+use orbital_bus::dynamics::{Atmosphere, Celestial, Gravity, Integrator, Orbit, Physics, Position, Velocity, orbital_period, system_drag, system_gravity, system_orbit};
+use orbital_bus::timestep::PHYSICS_RATE;
+
+const GM: f64 = 1e6;
+
+fn newton() -> Gravity {
+    Gravity::Newton { g: 1000.0, softening: 0.0 }
+}
+
+/// World with a fixed planet of mass 1000 at the origin and a body on a circular orbit of radius 200 around it
+fn circular_orbit(integrator: Integrator) -> (World, Entity) {
+    let mut world = World::new();
+    world.spawn((Physics { integrator, gravity: newton(), nbody: false },));
+    world.spawn((Position { x: 0.0, y: 0.0 }, Celestial { mass: 1000.0, radius: 10.0 }));
+    let speed = (GM / 200.0).sqrt();
+    let body = world.spawn((Position { x: 200.0, y: 0.0 }, Velocity { x: 0.0, y: speed }));
+    (world, body)
+}
+
+/// Kinetic plus potential energy of `body` around the planet at the origin
+fn energy(world: &World, body: Entity) -> f64 {
+    let position = world.get::<Position>(body).unwrap();
+    let velocity = world.get::<Velocity>(body).unwrap();
+    let r = (position.x.powi(2) + position.y.powi(2)).sqrt();
+    (velocity.x.powi(2) + velocity.y.powi(2)) / 2.0 - GM / r
+}
+
+/// Relative change of the energy after `seconds` of fixed steps
+fn energy_drift(integrator: Integrator, seconds: f64) -> f64 {
+    let (mut world, body) = circular_orbit(integrator);
+    let start = energy(&world, body);
+    for _ in 0..(seconds * PHYSICS_RATE) as usize {
+        system_gravity(&mut world, 1.0 / PHYSICS_RATE);
+    }
+    ((energy(&world, body) - start) / start).abs()
+}
+
+#[test]
+fn euler_gains_energy() {
+    assert!(energy_drift(Integrator::Euler, 300.0) > 0.1);
+}
+
+#[test]
+fn other_integrators_keep_energy_bounded() {
+    for integrator in [Integrator::SemiImplicitEuler, Integrator::Verlet, Integrator::Rk4] {
+        let drift = energy_drift(integrator, 300.0);
+        assert!(drift < 1e-5, "drift {}", drift);
+    }
+}
+
+/// Velocity gained by a body at rest at `(x, 0)` in one step towards a planet of mass 1000 at the origin
+fn pull(gravity: Gravity, x: f64) -> f64 {
+    let mut world = World::new();
+    world.spawn((Physics { gravity, ..Physics::default() },));
+    world.spawn((Position { x: 0.0, y: 0.0 }, Celestial { mass: 1000.0, radius: 10.0 }));
+    let body = world.spawn((Position { x, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }));
+    system_gravity(&mut world, 0.5);
+    let velocity = world.get::<Velocity>(body).unwrap();
+    assert_eq!(velocity.y, 0.0);
+    -velocity.x / 0.5
+}
+
+#[test]
+fn newton_falls_off_with_the_square_of_the_distance() {
+    let near = pull(newton(), 100.0);
+    assert!((near - GM / 100.0f64.powi(2)).abs() < 1e-9);
+    assert!((near / pull(newton(), 200.0) - 4.0).abs() < 1e-9);
+    assert!((near / pull(newton(), 1000.0) - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn newton_softening_caps_the_pull() {
+    let gravity = Gravity::Newton { g: 1000.0, softening: 100.0 };
+    // r^2 + softening^2 = 2 * 100^2
+    assert!((pull(gravity, 100.0) - GM * 100.0 / (2.0 * 100.0f64.powi(2)).powf(1.5)).abs() < 1e-9);
+    assert_eq!(pull(gravity, 0.0), 0.0);
+}
+
+#[test]
+fn classic_gravity_is_unchanged() {
+    // Linear in the distance: (celestial - position) * mass
+    assert_eq!(pull(Gravity::Classic, 100.0), 100_000.0);
+    assert_eq!(pull(Gravity::Classic, 200.0), 200_000.0);
+
+    let mut world = World::new();
+    world.spawn((Position { x: 0.0, y: 0.0 }, Celestial { mass: 0.5, radius: 10.0 }));
+    let body = world.spawn((Position { x: 10.0, y: -4.0 }, Velocity { x: 1.0, y: 2.0 }));
+    // Without a Physics singleton: semi-implicit Euler and classic gravity, as before
+    system_gravity(&mut world, 0.1);
+    let velocity = world.get::<Velocity>(body).unwrap();
+    let position = world.get::<Position>(body).unwrap();
+    assert!((velocity.x - 0.5).abs() < 1e-12);
+    assert!((velocity.y - 2.2).abs() < 1e-12);
+    assert!((position.x - 10.05).abs() < 1e-12);
+    assert!((position.y + 3.78).abs() < 1e-12);
+}
+
+fn momentum(world: &World) -> (f64, f64) {
+    world.query::<(&Velocity, &Celestial)>().iter().fold((0.0, 0.0), |(px, py), (_id, (velocity, celestial))| {
+        (px + velocity.x * celestial.mass, py + velocity.y * celestial.mass)
+    })
+}
+
+#[test]
+fn nbody_conserves_momentum() {
+    for integrator in [Integrator::Euler, Integrator::SemiImplicitEuler, Integrator::Verlet, Integrator::Rk4] {
+        let mut world = World::new();
+        world.spawn((Physics { integrator, gravity: Gravity::Newton { g: 1000.0, softening: 5.0 }, nbody: true },));
+        let star = world.spawn((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 0.0 }, Celestial { mass: 1000.0, radius: 20.0 }));
+        world.spawn((Position { x: 300.0, y: 0.0 }, Velocity { x: 0.0, y: 55.0 }, Celestial { mass: 20.0, radius: 5.0 }));
+        world.spawn((Position { x: -150.0, y: 200.0 }, Velocity { x: 30.0, y: 10.0 }, Celestial { mass: 5.0, radius: 5.0 }));
+        let start = momentum(&world);
+        for _ in 0..1200 {
+            system_gravity(&mut world, 1.0 / PHYSICS_RATE);
+        }
+        let (px, py) = momentum(&world);
+        assert!((px - start.0).abs() < 1e-6 && (py - start.1).abs() < 1e-6, "{:?} became {:?}", start, (px, py));
+        // The star is pulled by the planets, not only the other way around
+        let velocity = world.get::<Velocity>(star).unwrap();
+        assert!(velocity.x != 1.0 && velocity.y != 0.0);
+    }
+}
+
+#[test]
+fn kepler_orbits_return_after_their_period() {
+    for gravity in [Gravity::Classic, newton()] {
+        let mut world = World::new();
+        let parent = world.spawn((Position { x: 50.0, y: -20.0 }, Celestial { mass: 1000.0, radius: 10.0 }));
+        let period = orbital_period(gravity, 1000.0, 300.0);
+        let orbit = Orbit { parent, semi_major_axis: 300.0, eccentricity: 0.4, phase: 0.0, periapsis: 0.5, clockwise: false, period, time: 0.0 };
+        let body = world.spawn((Position { x: 0.0, y: 0.0 }, orbit));
+        let steps = 1000;
+        let distance = |world: &World| {
+            let position = world.get::<Position>(body).unwrap();
+            ((position.x - 50.0).powi(2) + (position.y + 20.0).powi(2)).sqrt()
+        };
+        system_orbit(&mut world, 0.0);
+        let start = Position::clone(&world.get::<Position>(body).unwrap());
+        // Starts at the periapsis, a * (1 - e) from the parent
+        assert!((distance(&world) - 180.0).abs() < 1e-9);
+        for _ in 0..steps / 2 {
+            system_orbit(&mut world, period / steps as f64);
+        }
+        // Half a period later it is at the apoapsis, a * (1 + e) away
+        assert!((distance(&world) - 420.0).abs() < 1e-6);
+        for _ in 0..steps / 2 {
+            system_orbit(&mut world, period / steps as f64);
+        }
+        let end = world.get::<Position>(body).unwrap();
+        assert!((end.x - start.x).abs() < 1e-6 && (end.y - start.y).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn newton_period_matches_the_integrated_orbit() {
+    let (mut world, body) = circular_orbit(Integrator::Verlet);
+    let period = orbital_period(newton(), 1000.0, 200.0);
+    let steps = (period * PHYSICS_RATE).round() as usize;
+    for _ in 0..steps {
+        system_gravity(&mut world, period / steps as f64);
+    }
+    let position = world.get::<Position>(body).unwrap();
+    assert!((position.x - 200.0).abs() < 0.01 && position.y.abs() < 0.01, "ended at ({}, {})", position.x, position.y);
+}
+
+#[test]
+fn drag_slows_bodies_inside_the_atmosphere() {
+    let mut world = World::new();
+    let planet = world.spawn((Position { x: 0.0, y: 0.0 }, Velocity { x: 10.0, y: 0.0 }, Celestial { mass: 0.0, radius: 50.0 }));
+    world.spawn((Position { x: 0.0, y: 0.0 }, Atmosphere { planet, radius: 100.0, density: 2.0 }));
+    let inside = world.spawn((Position { x: 0.0, y: 80.0 }, Velocity { x: 30.0, y: -40.0 }));
+    let outside = world.spawn((Position { x: 0.0, y: 120.0 }, Velocity { x: 30.0, y: -40.0 }));
+    system_drag(&mut world, 0.25);
+
+    // Only the speed relative to the planet is damped
+    let damping = (-2.0f64 * 0.25).exp();
+    let velocity = world.get::<Velocity>(inside).unwrap();
+    assert!((velocity.x - (10.0 + 20.0 * damping)).abs() < 1e-12);
+    assert!((velocity.y - (-40.0 * damping)).abs() < 1e-12);
+    let velocity = world.get::<Velocity>(outside).unwrap();
+    assert_eq!((velocity.x, velocity.y), (30.0, -40.0));
+    // The planet itself is not slowed by its own air
+    let velocity = world.get::<Velocity>(planet).unwrap();
+    assert_eq!((velocity.x, velocity.y), (10.0, 0.0));
+}
@@ -0,0 +1,82 @@
+use orbital_bus::SpaceShip;
+use orbital_bus::dynamics::{Position, Velocity};
+use orbital_bus::input::Input;
+use orbital_bus::landing::Landed;
+use orbital_bus::simulation::Simulation;
+
+mod common;
+
+/// A massless planet of radius 50 centred at (0, 200), so its top is at y = 150
+fn level(x: f64, y: f64, vx: f64, vy: f64, fuel: f64, refuel: bool) -> Simulation {
+    common::simulation(&common::pack(50.0, 0.0, &format!(r#"planets: [(ref: "rock", x: 0.0, y: 200.0)],
+        spaceship: (x: {}, y: {}, vx: {}, vy: {}, fuel: {}),
+        end: (x: 5000.0, y: 5000.0),
+        landing: (speed: 80.0, angle: 45.0, refuel: {}),"#, x, y, vx, vy, fuel, refuel)))
+}
+
+fn landed(simulation: &Simulation) -> bool {
+    simulation.world.query::<&Landed>().iter().count() == 1
+}
+
+fn ship(simulation: &Simulation) -> (Position, (f64, f64), f64) {
+    let mut query = simulation.world.query::<(&SpaceShip, &Position, &Velocity)>();
+    let (_id, (spaceship, position, velocity)) = query.iter().next().unwrap();
+    (position.clone(), (velocity.x, velocity.y), spaceship.fuel)
+}
+
+#[test]
+fn soft_landing_rests_on_the_surface() {
+    let mut simulation = level(0.0, 0.0, 0.0, 50.0, 10.0, false);
+    simulation.run(&Input::default(), 5.0);
+    assert!(landed(&simulation));
+    assert!(!simulation.crashed());
+    let (position, velocity, _fuel) = ship(&simulation);
+    assert!(position.x.abs() < 1e-9 && (position.y - 150.0).abs() < 1e-9, "{} {}", position.x, position.y);
+    assert_eq!(velocity, (0.0, 0.0));
+}
+
+#[test]
+fn fast_landing_crashes() {
+    let mut simulation = level(0.0, 0.0, 0.0, 120.0, 10.0, false);
+    simulation.run(&Input::default(), 5.0);
+    assert!(simulation.crashed());
+    assert!(!landed(&simulation));
+}
+
+#[test]
+fn steep_landing_crashes() {
+    // Hits the planet at (-30, 160), 53 degrees away from a vertical approach
+    let mut simulation = level(-200.0, 160.0, 50.0, 0.0, 10.0, false);
+    simulation.run(&Input::default(), 5.0);
+    assert!(simulation.crashed());
+}
+
+#[test]
+fn landing_refuels() {
+    let mut simulation = level(0.0, 0.0, 0.0, 50.0, 10.0, true);
+    for (_id, spaceship) in simulation.world.query::<&mut SpaceShip>().iter() {
+        spaceship.fuel = 0.0;
+    }
+    simulation.run(&Input::default(), 5.0);
+    assert!(landed(&simulation));
+    assert_eq!(ship(&simulation).2, 10.0);
+}
+
+#[test]
+fn take_off_needs_fuel() {
+    let thrust = Input { forward: true, ..Input::default() };
+
+    let mut simulation = level(0.0, 0.0, 0.0, 50.0, 0.5, false);
+    simulation.run(&Input::default(), 5.0);
+    simulation.run(&thrust, 1.0);
+    assert!(landed(&simulation));
+    assert_eq!(ship(&simulation).2, 0.5);
+
+    let mut simulation = level(0.0, 0.0, 0.0, 50.0, 10.0, false);
+    simulation.run(&Input::default(), 5.0);
+    simulation.run(&thrust, 0.1);
+    assert!(!landed(&simulation));
+    let (position, velocity, fuel) = ship(&simulation);
+    assert!(position.y < 150.0 && velocity.1 < 0.0);
+    assert!((0.0..10.0).contains(&fuel));
+}
@@ -0,0 +1,118 @@
+use orbital_bus::{EndZone, ImageStore};
+use orbital_bus::dynamics::{Celestial, Position};
+use orbital_bus::input::Input;
+use orbital_bus::levels::Levels;
+use orbital_bus::simulation::Simulation;
+
+mod common;
+
+fn pack() -> String {
+    common::pack(10.0, 1.0, r#"planets: [(ref: "rock", x: 100.0, y: 100.0), (ref: "rock", x: 200.0, y: 100.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 0.0, vy: 0.0, fuel: 1.0),
+        end: (x: 500.0, y: 500.0),"#)
+}
+
+fn planets(levels: &Levels, level: i32) -> usize {
+    let mut store = ImageStore::new();
+    let world = levels.load_level(level, &mut store);
+    let count = world.query::<&Celestial>().iter().count();
+    count
+}
+
+#[test]
+fn packs_override_builtin_levels() {
+    let mut levels = Levels::builtin();
+    let builtin = planets(&levels, 2);
+    levels.add_pack(&pack()).unwrap();
+    assert_eq!(planets(&levels, 1), 2);
+    assert_eq!(planets(&levels, 2), builtin);
+    assert_eq!(levels.packs(), vec!["Test 1 by tests".to_string(), "Orbital Bus 0.1.0 by Adrián Arroyo Calle".to_string()]);
+}
+
+#[test]
+fn later_packs_override_earlier_ones() {
+    let mut levels = Levels::builtin();
+    levels.add_pack(&pack()).unwrap();
+    let single = pack()
+        .replace(r#"name: "Test""#, r#"name: "Later""#)
+        .replace(r#", (ref: "rock", x: 200.0, y: 100.0)"#, "");
+    levels.add_pack(&single).unwrap();
+    assert_eq!(planets(&levels, 1), 1);
+    assert_eq!(levels.packs(), vec![
+        "Later 1 by tests".to_string(),
+        "Test 1 by tests".to_string(),
+        "Orbital Bus 0.1.0 by Adrián Arroyo Calle".to_string(),
+    ]);
+}
+
+#[test]
+fn broken_packs_are_rejected() {
+    let mut levels = Levels::builtin();
+    assert!(levels.add_pack("Pack(name: \"x\")").is_err());
+    assert_eq!(levels.packs().len(), 1);
+}
+
+#[test]
+fn objectives_about_missing_planets_are_rejected() {
+    let mut levels = Levels::builtin();
+    for objective in ["Orbits(planet: 2, count: 1.0)", "All([Checkpoints(points: [(x: 0.0, y: 50.0)]), Orbits(planet: 5, count: 1.0)])"] {
+        let source = pack().replace("end: (x: 500.0, y: 500.0),", &format!("end: (x: 500.0, y: 500.0), objective: {},", objective));
+        let error = levels.add_pack(&source).err().unwrap();
+        assert!(error.to_string().contains("world 1: objective orbits missing planet"), "{}", error);
+    }
+    // A planet whose ref is unknown is not spawned either
+    let source = pack()
+        .replace(r#"(ref: "rock", x: 200.0, y: 100.0)"#, r#"(ref: "moon", x: 200.0, y: 100.0)"#)
+        .replace("end: (x: 500.0, y: 500.0),", "end: (x: 500.0, y: 500.0), objective: Orbits(planet: 1, count: 1.0),");
+    assert!(levels.add_pack(&source).is_err());
+    assert_eq!(levels.packs().len(), 1);
+
+    let source = pack().replace("end: (x: 500.0, y: 500.0),", "end: (x: 500.0, y: 500.0), objective: Orbits(planet: 1, count: 1.0),");
+    levels.add_pack(&source).unwrap();
+}
+
+#[test]
+fn planet_radius_must_be_positive() {
+    let mut levels = Levels::builtin();
+    let error = levels.add_pack(&pack().replace("radius: 10.0", "radius: 0.0")).err().unwrap();
+    assert!(error.to_string().contains("planet radius must be positive"), "{}", error);
+    assert_eq!(levels.packs().len(), 1);
+}
+
+#[test]
+fn planets_override_their_definition() {
+    let mut levels = Levels::builtin();
+    levels.add_pack(&pack().replace(
+        r#"(ref: "rock", x: 200.0, y: 100.0)"#,
+        r#"(ref: "rock", x: 200.0, y: 100.0, mass: 3.0, radius: 20.0, sprite: "earth.png")"#,
+    )).unwrap();
+    let mut store = ImageStore::new();
+    let world = levels.load_level(1, &mut store);
+    let mut bodies: Vec<(f64, f64)> = world.query::<&Celestial>().iter().map(|(_id, planet)| (planet.mass, planet.radius)).collect();
+    bodies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(bodies, vec![(1.0, 10.0), (3.0, 20.0)]);
+    assert!(store.contains_key("earth.png"));
+}
+
+#[test]
+fn attached_end_zone_follows_its_planet() {
+    let mut levels = Levels::builtin();
+    levels.add_pack(&pack()
+        .replace(r#"fix: true"#, r#"fix: false"#)
+        .replace(r#"(ref: "rock", x: 100.0, y: 100.0)"#, r#"(ref: "rock", x: 100.0, y: 100.0, mass: 0.0, vx: 0.0, vy: 0.0)"#)
+        .replace(r#"(ref: "rock", x: 200.0, y: 100.0)"#, r#"(ref: "rock", x: 200.0, y: 100.0, mass: 0.0, vx: 50.0, vy: 0.0)"#)
+        .replace(r#"end: (x: 500.0, y: 500.0)"#, r#"end: (x: 0.0, y: -100.0, attach: 1)"#)
+    ).unwrap();
+    let mut simulation = Simulation::with_levels(&levels, 1);
+    simulation.run(&Input::default(), 1.0);
+
+    let world = &simulation.world;
+    let zone = world.query::<(&EndZone, &Position)>().iter().map(|(_id, (_zone, position))| position.clone()).next().unwrap();
+    let planet = world.query::<(&Celestial, &Position)>().iter()
+        .map(|(_id, (_celestial, position))| position.clone())
+        .find(|position| position.x > 200.0)
+        .unwrap();
+    assert!((planet.x - 250.0).abs() < 1.0);
+    assert!((zone.x - (planet.x - 50.0)).abs() < 1e-9);
+    assert!((zone.y - (planet.y - 150.0)).abs() < 1e-9);
+}
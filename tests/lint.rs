@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use orbital_bus::levels::lint;
+
+const SHIP: &str = "spaceship: (x: 0.0, y: 0.0, vx: 0.0, vy: 0.0, fuel: 1.0)";
+
+#[test]
+fn builtin_levels_are_clean() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let source = std::fs::read_to_string(root.join("src/levels/levels.ron")).unwrap();
+    let issues = lint(&source, |sprite| root.join("public").join(sprite).is_file());
+    let messages: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.is_empty(), "{:?}", messages);
+}
+
+#[test]
+fn parse_errors_have_a_position() {
+    let issues = lint("Pack(planets: [, worlds: [])", |_| true);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].position.map(|(line, _)| line), Some(1));
+}
+
+#[test]
+fn reports_broken_references() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"mars\", x: 500.0, y: 500.0)], {ship}, end: (x: 900.0, y: 900.0, visit: 3)),
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 0.0, y: 0.0)], {ship}, end: (x: 900.0, y: 900.0)),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.iter().any(|m| m.contains("unknown ref 'mars'")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("missing planet 3")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("duplicate world id")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("spaceship spawns inside planet 0")), "{:?}", messages);
+}
+
+#[test]
+fn reports_atmosphere_inside_planet() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true, atmosphere: (radius: 40.0, density: 1.0))], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (x: 900.0, y: 900.0)),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.iter().any(|m| m.contains("planet 'earth' has an atmosphere inside its surface")), "{:?}", messages);
+}
+
+#[test]
+fn reports_non_positive_planet_radius() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 0.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (x: 900.0, y: 900.0)),
+        ])",
+        ship = SHIP
+    );
+    let issues = lint(&source, |_| true);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("planet radius must be positive"), "{}", issues[0].message);
+}
+
+#[test]
+fn reports_end_zone_without_position() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (y: 900.0)),
+            World(id: 2, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (y: -300.0, attach: 0, shape: Circle(radius: 100.0))),
+            World(id: 3, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (shape: Annulus(planet: 0, inner: 100.0, outer: 200.0))),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.iter().any(|m| m == "world 1: end zone needs x and y unless it is attached to a planet"), "{:?}", messages);
+    assert!(!messages.iter().any(|m| m.contains("world 2") || m.contains("world 3")), "{:?}", messages);
+}
+
+#[test]
+fn reports_minimap_keys_already_bound() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0)], {ship}, end: (x: 900.0, y: 900.0), minimap: (key: 'c')),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(messages, vec!["world 1: minimap key 'c' is already bound".to_string()]);
+}
+
+#[test]
+fn follows_orbits_of_orbiting_parents() {
+    // The moon starts 300 pixels right of its planet, itself 1000 pixels right of the sun: on the spaceship
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [
+                (ref: \"earth\", x: -1300.0, y: 0.0),
+                (ref: \"earth\", x: 0.0, y: 0.0, orbit: (parent: 0, semi_major_axis: 1000.0)),
+                (ref: \"earth\", x: 0.0, y: 0.0, orbit: (parent: 1, semi_major_axis: 300.0)),
+            ], {ship}, end: (x: 900.0, y: 900.0)),
+            World(id: 2, background: true, planets: [
+                (ref: \"earth\", x: 500.0, y: 0.0, orbit: (parent: 1, semi_major_axis: 300.0)),
+                (ref: \"earth\", x: 500.0, y: 0.0, orbit: (parent: 0, semi_major_axis: 300.0)),
+            ], {ship}, end: (x: 900.0, y: 900.0)),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.iter().any(|m| m == "world 1: spaceship spawns inside planet 2 ('earth')"), "{:?}", messages);
+    assert!(!messages.iter().any(|m| m.contains("world 1: spaceship spawns inside planet 1")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m == "world 2: planet 0 orbits in a cycle or more than 16 levels deep"), "{:?}", messages);
+}
+
+#[test]
+fn reports_planets_inside_an_annulus_band() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [
+                (ref: \"earth\", x: 500.0, y: 500.0),
+                (ref: \"earth\", x: 500.0, y: 740.0),
+                (ref: \"earth\", x: 500.0, y: 900.0),
+            ], {ship}, end: (shape: Annulus(planet: 0, inner: 100.0, outer: 200.0))),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(messages, vec!["world 1: end zone overlaps planet 1 ('earth')".to_string()]);
+}
+
+#[test]
+fn reports_objectives_about_missing_planets() {
+    let source = format!(
+        "Pack(name: \"test\", author: \"tests\", version: \"1\", planets: [PlanetDef(id: \"earth\", sprite: \"earth.png\", radius: 50.0, mass: 1.0, fix: true)], worlds: [
+            World(id: 1, background: true, planets: [(ref: \"earth\", x: 500.0, y: 500.0), (ref: \"moon\", x: 900.0, y: 500.0)], {ship}, end: (x: 900.0, y: 900.0),
+                objective: Any([Orbits(planet: 1, count: 1.0), Orbits(planet: 2, count: 1.0), Orbits(planet: 0, count: 1.0)])),
+        ])",
+        ship = SHIP
+    );
+    let messages: Vec<String> = lint(&source, |_| true).iter().map(|issue| issue.to_string()).collect();
+    assert!(messages.iter().any(|m| m == "world 1: objective orbits missing planet 1"), "{:?}", messages);
+    assert!(messages.iter().any(|m| m == "world 1: objective orbits missing planet 2"), "{:?}", messages);
+    assert!(!messages.iter().any(|m| m.contains("missing planet 0")), "{:?}", messages);
+}
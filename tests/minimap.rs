@@ -0,0 +1,95 @@
+use hecs::{Entity, World};
+
+use orbital_bus::input::Input;
+use orbital_bus::minimap::{Corner, Minimap, MinimapMark, system_minimap};
+use orbital_bus::dynamics::Position;
+use orbital_bus::renderer::{Renderer, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+mod common;
+
+fn load() -> World {
+    common::load(&common::pack(50.0, 0.0, &format!("{}\n        minimap: (corner: BottomLeft, size: 100.0, key: 'n'),", common::WIDE)))
+}
+
+fn marks(world: &World) -> usize {
+    world.query::<&MinimapMark>().iter().count()
+}
+
+#[test]
+fn levels_configure_the_minimap() {
+    let world = load();
+    let mut query = world.query::<&Minimap>();
+    let (_id, minimap) = query.iter().next().unwrap();
+    assert_eq!(minimap.corner, Corner::BottomLeft);
+    assert_eq!(minimap.size, 100.0);
+    assert_eq!(minimap.key, 'N' as u32);
+    assert!(minimap.visible);
+}
+
+#[test]
+fn everything_is_drawn_inside_the_corner() {
+    let mut world = load();
+    system_minimap(&mut world, &Input::default());
+    // Background, end zone, two planets and the spaceship
+    assert_eq!(marks(&world), 5);
+    let mut query = world.query::<(&Renderer, &Position, &MinimapMark)>();
+    for (_id, (renderer, position, _mark)) in query.iter() {
+        assert!(renderer.is_fixed());
+        // Right of the controls and 60 pixels above the bottom border
+        assert!(position.x >= 280.0 && position.x <= 380.0, "x {}", position.x);
+        assert!(position.y >= SCREEN_HEIGHT - 160.0 && position.y <= SCREEN_HEIGHT - 60.0, "y {}", position.y);
+    }
+}
+
+#[test]
+fn key_toggles_the_minimap() {
+    let mut world = load();
+    let toggle = Input { keys: vec!['N' as u32], ..Input::default() };
+    system_minimap(&mut world, &toggle);
+    assert_eq!(marks(&world), 0);
+    system_minimap(&mut world, &Input::default());
+    assert_eq!(marks(&world), 0);
+    system_minimap(&mut world, &toggle);
+    assert_eq!(marks(&world), 5);
+}
+
+#[test]
+fn marks_are_updated_in_place() {
+    let mut world = load();
+    system_minimap(&mut world, &Input::default());
+    let mut before: Vec<Entity> = world.query::<&MinimapMark>().iter().map(|(id, _mark)| id).collect();
+    system_minimap(&mut world, &Input::default());
+    let mut after: Vec<Entity> = world.query::<&MinimapMark>().iter().map(|(id, _mark)| id).collect();
+    before.sort();
+    after.sort();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn corners_stay_clear_of_the_hud() {
+    // Back/restart buttons and camera readout, thrust/brake controls and fuel gauge as (x, y, width, height)
+    let hud = [(10.0, 10.0, 250.0, 240.0), (10.0, 500.0, 250.0, 279.0), (1230.0, 630.0, 133.0, 133.0)];
+    for corner in [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight] {
+        let minimap = Minimap { corner, ..Minimap::default() };
+        let origin = minimap.origin();
+        let (x, y, size) = (origin.x, origin.y, minimap.size);
+        assert!(x >= 0.0 && y >= 0.0 && x + size <= SCREEN_WIDTH && y + size <= SCREEN_HEIGHT, "{:?} off screen", corner);
+        for (left, top, width, height) in hud {
+            let apart = x + size <= left || left + width <= x || y + size <= top || top + height <= y;
+            assert!(apart, "{:?} covers the HUD at ({}, {})", corner, left, top);
+        }
+    }
+}
+
+#[test]
+fn zones_keep_their_colour() {
+    let pack = common::pack(50.0, 0.0, &common::WIDE.replace("end: (x: 500.0, y: 0.0),", r#"end: (x: 500.0, y: 0.0, color: "blue"),"#));
+    let mut world = common::load(&pack);
+    system_minimap(&mut world, &Input::default());
+    let mut query = world.query::<(&Renderer, &MinimapMark)>();
+    let colors: Vec<String> = query.iter()
+        .filter(|(_id, (_renderer, mark))| matches!(mark, MinimapMark::Zone(_)))
+        .map(|(_id, (renderer, _mark))| renderer.get_color().unwrap().clone())
+        .collect();
+    assert_eq!(colors, vec!["blue".to_string()]);
+}
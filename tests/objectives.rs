@@ -0,0 +1,99 @@
+use orbital_bus::input::Input;
+use orbital_bus::simulation::Simulation;
+
+mod common;
+
+/// A ship drifting right at 100 px/s next to a massless planet
+fn level(objective: &str) -> Simulation {
+    level_with_end("(x: 300.0, y: 0.0)", objective)
+}
+
+fn level_with_end(end: &str, objective: &str) -> Simulation {
+    common::simulation(&common::pack(10.0, 0.0, &format!(r#"planets: [(ref: "rock", x: 0.0, y: 5000.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 100.0, vy: 0.0, fuel: 1.0),
+        end: {},
+        objective: {},"#, end, objective)))
+}
+
+#[test]
+fn survive_waits_for_the_time() {
+    let mut simulation = level("Survive(seconds: 2.0)");
+    simulation.run(&Input::default(), 1.5);
+    assert!(!simulation.reached());
+    simulation.run(&Input::default(), 1.0);
+    assert!(simulation.reached());
+}
+
+#[test]
+fn checkpoints_are_taken_in_order() {
+    let mut simulation = level("Checkpoints(points: [(x: 200.0, y: 0.0), (x: 100.0, y: 0.0)], radius: 20.0)");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level("Checkpoints(points: [(x: 100.0, y: 0.0), (x: 200.0, y: 0.0)], radius: 20.0)");
+    simulation.run(&Input::default(), 4.0);
+    assert!(simulation.reached());
+}
+
+#[test]
+fn slow_reach_rejects_fast_ships() {
+    let mut simulation = level("Reach(speed: 50.0)");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level("Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(simulation.reached());
+}
+
+#[test]
+fn combinations() {
+    let mut simulation = level("All([Survive(seconds: 1.0), Survive(seconds: 60.0)])");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level("Any([Survive(seconds: 1.0), Survive(seconds: 60.0)])");
+    simulation.run(&Input::default(), 4.0);
+    assert!(simulation.reached());
+}
+
+#[test]
+fn zone_shapes() {
+    let mut simulation = level_with_end("(x: 300.0, y: 200.0, shape: Circle(radius: 50.0))", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level_with_end("(x: 300.0, y: 40.0, shape: Circle(radius: 50.0), margin: 0.0)", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(simulation.reached());
+
+    let mut simulation = level_with_end("(shape: Annulus(planet: 0, inner: 4800.0, outer: 4900.0))", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level_with_end("(shape: Annulus(planet: 0, inner: 4900.0, outer: 5100.0))", "Reach()");
+    simulation.run(&Input::default(), 1.0);
+    assert!(simulation.reached());
+}
+
+#[test]
+fn docking_needs_matched_velocity() {
+    let mut simulation = level_with_end("(x: 300.0, y: 0.0, dock: (speed: 50.0))", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+
+    let mut simulation = level_with_end("(x: 300.0, y: 0.0, dock: (speed: 150.0, angle: 30.0, direction: 0.0))", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(simulation.reached());
+
+    let mut simulation = level_with_end("(x: 300.0, y: 0.0, dock: (speed: 150.0, angle: 30.0, direction: 180.0))", "Reach()");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+}
+
+#[test]
+fn deliver_needs_passengers() {
+    let mut simulation = level("Deliver");
+    simulation.run(&Input::default(), 4.0);
+    assert!(!simulation.reached());
+}
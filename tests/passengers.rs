@@ -0,0 +1,39 @@
+use orbital_bus::input::Input;
+use orbital_bus::passengers::count;
+use orbital_bus::simulation::Simulation;
+
+mod common;
+
+/// A ship drifting right at 100 px/s through two stops
+fn level(capacity: u32, speed: f64) -> Simulation {
+    common::simulation(&common::pack(10.0, 0.0, &format!(r#"planets: [(ref: "rock", x: 0.0, y: 5000.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 100.0, vy: 0.0, fuel: 1.0, capacity: {}),
+        end: (x: 900.0, y: 900.0),
+        stops: [(x: 100.0, y: 0.0, radius: 30.0, speed: {}), (x: 300.0, y: 0.0, radius: 30.0, speed: {})],
+        passengers: [(from: 0, to: 1, count: 3)],"#, capacity, speed, speed)))
+}
+
+#[test]
+fn passengers_need_room_aboard() {
+    let mut simulation = level(2, 150.0);
+    simulation.run(&Input::default(), 2.0);
+    assert_eq!(count(&simulation.world), (2, 0, 3));
+    simulation.run(&Input::default(), 2.0);
+    assert_eq!(count(&simulation.world), (0, 2, 3));
+    assert!(!simulation.reached());
+}
+
+#[test]
+fn delivering_everyone_wins() {
+    let mut simulation = level(3, 150.0);
+    simulation.run(&Input::default(), 4.0);
+    assert_eq!(count(&simulation.world), (0, 3, 3));
+    assert!(simulation.reached());
+}
+
+#[test]
+fn fast_ships_do_not_stop() {
+    let mut simulation = level(3, 50.0);
+    simulation.run(&Input::default(), 4.0);
+    assert_eq!(count(&simulation.world), (0, 0, 3));
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use orbital_bus::ImageStore;
+use orbital_bus::backend::Backend;
+use orbital_bus::dynamics::Position;
+use orbital_bus::levels::Levels;
+use orbital_bus::raster::{Bitmap, Raster, parse_color};
+use orbital_bus::renderer::system_renderer;
+
+/// Compares with `tests/golden/<name>.pam`. Run with `UPDATE_GOLDEN=1` to write the images again
+fn assert_golden(name: &str, bitmap: &Bitmap) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", &format!("{}.pam", name)].iter().collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, bitmap.encode_pam()).unwrap();
+        return;
+    }
+    let data = std::fs::read(&path).unwrap_or_else(|_| panic!("missing {}, run with UPDATE_GOLDEN=1", path.display()));
+    let golden = Bitmap::decode_pam(&data).unwrap();
+    assert_eq!((bitmap.width, bitmap.height), (golden.width, golden.height));
+    let different = bitmap.pixels.chunks(4).zip(golden.pixels.chunks(4)).filter(|(a, b)| a != b).count();
+    assert_eq!(different, 0, "{} pixels differ from {}", different, path.display());
+}
+
+#[test]
+fn colors_are_parsed() {
+    assert_eq!(parse_color("#37a0fa"), Some([0x37, 0xa0, 0xfa, 255]));
+    assert_eq!(parse_color("#fff"), Some([255, 255, 255, 255]));
+    assert_eq!(parse_color("rgba(250, 126, 55, 0.7)"), Some([250, 126, 55, 179]));
+    assert_eq!(parse_color("white"), Some([255, 255, 255, 255]));
+    assert_eq!(parse_color("chartreuse-ish"), None);
+}
+
+#[test]
+fn pam_round_trips() {
+    let bitmap = Bitmap::filled(3, 2, [1, 2, 3, 4]);
+    assert_eq!(Bitmap::decode_pam(&bitmap.encode_pam()), Some(bitmap));
+}
+
+#[test]
+fn translucent_colours_blend_source_over() {
+    let mut raster = Raster::new(2, 1);
+    let half_red = "rgba(255, 0, 0, 0.5)";
+    // Over transparent pixels the colour is kept and only the alpha is partial
+    raster.rect(&Position { x: 0.0, y: 0.0 }, 2.0, 1.0, half_red);
+    assert_eq!(raster.bitmap().pixel(0, 0), [255, 0, 0, 128]);
+    raster.rect(&Position { x: 1.0, y: 0.0 }, 1.0, 1.0, "rgba(0, 0, 255, 0.5)");
+    assert_eq!(raster.bitmap().pixel(1, 0), [85, 0, 170, 192]);
+}
+
+#[test]
+fn primitives_match_golden() {
+    let mut raster = Raster::new(128, 96);
+    raster.rect(&Position { x: 0.0, y: 0.0 }, 128.0, 96.0, "#1b1b1b");
+    raster.circle(&Position { x: 24.0, y: 24.0 }, 16.0, "red");
+    raster.ring(&Position { x: 64.0, y: 24.0 }, 8.0, 16.0, "rgba(55, 126, 250, 0.7)");
+    raster.rect(&Position { x: 90.0, y: 10.0 }, 30.0, 20.0, "rgba(126, 250, 55, 0.4)");
+    raster.text("Hi 2", &Position { x: 8.0, y: 80.0 }, "20px Tsoonami", "white");
+    raster.add_image("tile.png", Bitmap::filled(2, 2, [250, 220, 55, 255]));
+    // Rotated image around its centre, as the spaceship is drawn
+    raster.save();
+    raster.translate(96.0, 68.0);
+    raster.rotate(std::f64::consts::FRAC_PI_4);
+    raster.image("tile.png", &Position { x: -10.0, y: -10.0 }, 20.0, 20.0);
+    raster.restore();
+    raster.circle(&Position { x: 64.0, y: 68.0 }, 6.0, "not a colour");
+    assert_golden("primitives", raster.bitmap());
+}
+
+#[test]
+fn first_level_matches_golden() {
+    let mut store = ImageStore::new();
+    let mut world = Levels::builtin().load_level(1, &mut store);
+    // Quarter size keeps the golden image small
+    let mut raster = Raster::new(340, 192);
+    raster.scale(0.25, 0.25);
+    let mut names: Vec<&String> = store.keys().collect();
+    names.sort();
+    for (i, name) in names.into_iter().enumerate() {
+        let shade = 80 + (i as u8) * 20;
+        raster.add_image(name, Bitmap::filled(64, 64, [shade, 255 - shade, 160, 255]));
+    }
+    system_renderer(&mut world, &mut raster, 1.0);
+    assert_golden("level1", raster.bitmap());
+}
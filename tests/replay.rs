@@ -0,0 +1,60 @@
+use orbital_bus::input::Input;
+use orbital_bus::levels::Levels;
+use orbital_bus::replay::{Ending, MAX_STEPS, Recording};
+use orbital_bus::simulation::Simulation;
+
+mod common;
+
+#[test]
+fn replay_reproduces_run() {
+    let mut simulation = Simulation::new(1);
+    simulation.run(&Input::default(), 1.0);
+    let brake = Input {
+        brake: true,
+        ..Input::default()
+    };
+    simulation.run(&brake, 20.0);
+    assert_eq!(simulation.recording.ending, Some(Ending::Crash));
+
+    let recording = Recording::decode(&simulation.recording.encode()).unwrap();
+    assert_eq!(recording, simulation.recording);
+
+    let replay = Simulation::replay(&Levels::builtin(), &recording);
+    assert!(replay.crashed());
+    assert_eq!(replay.time, simulation.time);
+}
+
+#[test]
+fn decode_rejects_other_versions_and_bad_rates() {
+    let version = env!("CARGO_PKG_VERSION");
+    assert!(Recording::decode(&format!("obr1 {} 1 120 - 3n", version)).is_some());
+    assert!(Recording::decode("obr1 0.0.0-old 1 120 - 3n").is_none());
+    for rate in ["0", "-120", "inf", "NaN"] {
+        assert!(Recording::decode(&format!("obr1 {} 1 {} - 3n", version, rate)).is_none(), "{}", rate);
+    }
+}
+
+#[test]
+fn decode_rejects_overlong_recordings() {
+    let version = env!("CARGO_PKG_VERSION");
+    assert_eq!(Recording::decode(&format!("obr1 {} 1 120 - {}n", version, MAX_STEPS)).unwrap().steps.len(), MAX_STEPS);
+    assert!(Recording::decode(&format!("obr1 {} 1 120 - {}n1f", version, MAX_STEPS)).is_none());
+    assert!(Recording::decode(&format!("obr1 {} 1 120 - 18446744073709551615n", version)).is_none());
+    assert!(Recording::decode(&format!("obr1 {} 1 120 - 3600000n18446744073709551615f", version)).is_none());
+}
+
+#[test]
+fn replay_uses_the_given_levels() {
+    // Level 1 of the pack crashes into a planet, unlike the built-in level 1
+    let pack = common::pack(50.0, 0.0, r#"planets: [(ref: "rock", x: 300.0, y: 0.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 200.0, vy: 0.0, fuel: 1.0),
+        end: (x: -2000.0, y: 0.0),"#);
+    let levels = common::levels(&pack);
+    let mut simulation = Simulation::with_levels(&levels, 1);
+    simulation.run(&Input::default(), 5.0);
+    assert!(simulation.crashed());
+
+    let replay = Simulation::replay(&levels, &simulation.recording);
+    assert!(replay.crashed());
+    assert_eq!(replay.time, simulation.time);
+}
@@ -0,0 +1,30 @@
+use orbital_bus::input::Input;
+use orbital_bus::simulation::Simulation;
+
+#[test]
+fn every_level_loads() {
+    for level in 1..=7 {
+        let simulation = Simulation::new(level);
+        let ships = simulation.world.query::<&orbital_bus::SpaceShip>().iter().count();
+        assert_eq!(ships, 1, "level {} has no spaceship", level);
+    }
+}
+
+#[test]
+fn coasting_keeps_orbit() {
+    let mut simulation = Simulation::new(1);
+    simulation.run(&Input::default(), 20.0);
+    assert!(!simulation.crashed());
+    assert!(!simulation.reached());
+}
+
+#[test]
+fn braking_falls_into_planet() {
+    let mut simulation = Simulation::new(1);
+    let input = Input {
+        brake: true,
+        ..Input::default()
+    };
+    simulation.run(&input, 20.0);
+    assert!(simulation.crashed());
+}
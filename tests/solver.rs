@@ -0,0 +1,31 @@
+use orbital_bus::levels::Levels;
+use orbital_bus::replay::Ending;
+use orbital_bus::simulation::Simulation;
+use orbital_bus::solver::{SolverOptions, solve};
+
+mod common;
+
+#[test]
+fn solution_replays_to_a_win() {
+    let options = SolverOptions {
+        beam: 1,
+        segment: 60,
+        max_seconds: 8.0,
+    };
+    let solution = solve(&Levels::builtin(), 1, &options).expect("level 1 should be winnable");
+    assert_eq!(solution.recording.ending, Some(Ending::Win));
+    let replay = Simulation::replay(&Levels::builtin(), &solution.recording);
+    assert!(replay.reached());
+}
+
+#[test]
+fn solves_levels_from_packs() {
+    // Level 1 of the pack is won by coasting, unlike the built-in one
+    let pack = common::pack(50.0, 0.0, r#"planets: [(ref: "rock", x: 0.0, y: 600.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 150.0, vy: 0.0, fuel: 1.0),
+        end: (x: 500.0, y: 0.0),"#);
+    let levels = common::levels(&pack);
+    let solution = solve(&levels, 1, &SolverOptions::default()).expect("the pack level should be winnable");
+    assert_eq!(solution.fuel, 0.0);
+    assert!(Simulation::replay(&levels, &solution.recording).reached());
+}
@@ -0,0 +1,33 @@
+use orbital_bus::timestep::Timestep;
+
+#[test]
+fn steps_at_a_fixed_rate_and_keeps_the_remainder() {
+    let mut timestep = Timestep::new(100.0, 8);
+    assert_eq!(timestep.advance(0.025), 2);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-9);
+    // The leftover half step completes the next one
+    assert_eq!(timestep.advance(0.006), 1);
+    assert!((timestep.alpha() - 0.1).abs() < 1e-9);
+    assert_eq!(timestep.advance(0.0), 0);
+    assert!((timestep.alpha() - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn long_frames_are_clamped_to_max_substeps() {
+    let mut timestep = Timestep::new(100.0, 8);
+    assert_eq!(timestep.advance(5.0), 8);
+    // At most one step is carried over, so the next frame does not spiral
+    assert!(timestep.alpha() <= 1.0);
+    assert_eq!(timestep.advance(0.0), 1);
+    assert_eq!(timestep.advance(0.0), 0);
+}
+
+#[test]
+fn alpha_stays_in_range() {
+    let mut timestep = Timestep::new(120.0, 8);
+    for _ in 0..1000 {
+        timestep.advance(1.0 / 61.0);
+        let alpha = timestep.alpha();
+        assert!((0.0..=1.0).contains(&alpha), "{}", alpha);
+    }
+}
@@ -0,0 +1,52 @@
+use hecs::World;
+
+use orbital_bus::SpaceShip;
+use orbital_bus::dynamics::Position;
+use orbital_bus::input::Input;
+use orbital_bus::trajectory::predict;
+
+mod common;
+
+/// Spaceship coasting between two planets that pull it slightly
+const COAST: &str = r#"planets: [(ref: "rock", x: -1000.0, y: 0.0), (ref: "rock", x: 3000.0, y: 400.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 60.0, vy: -20.0, fuel: 1.0),
+        end: (x: 2000.0, y: 2000.0),"#;
+
+/// Spaceship heading straight into a planet 300 pixels away
+const COLLISION: &str = r#"planets: [(ref: "rock", x: 300.0, y: 0.0)],
+        spaceship: (x: 0.0, y: 0.0, vx: 200.0, vy: 0.0, fuel: 1.0),
+        end: (x: -2000.0, y: 0.0),"#;
+
+fn ship(world: &World) -> Position {
+    let mut query = world.query::<(&SpaceShip, &Position)>();
+    let (_id, (_ship, position)) = query.iter().next().unwrap();
+    position.clone()
+}
+
+#[test]
+fn prediction_matches_the_simulation() {
+    let mut simulation = common::simulation(&common::pack(50.0, 0.001, COAST));
+    let points = predict(&simulation.world, 2.0, simulation.step);
+    assert_eq!(points.len(), 40);
+    // One point every 6 steps
+    for point in points {
+        for _ in 0..6 {
+            simulation.step(&Input::default());
+        }
+        let position = ship(&simulation.world);
+        assert!((point.x - position.x).abs() < 1e-9 && (point.y - position.y).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn prediction_stops_before_a_crash() {
+    let mut simulation = common::simulation(&common::pack(50.0, 0.0, COLLISION));
+    let points = predict(&simulation.world, 5.0, simulation.step);
+    // About 1.25 seconds until it touches the planet
+    assert!(!points.is_empty() && points.len() < 30);
+    assert!(points.iter().all(|point| point.x < 250.0));
+
+    simulation.run(&Input::default(), 5.0);
+    assert!(simulation.crashed());
+    assert!(predict(&simulation.world, 5.0, simulation.step).is_empty());
+}